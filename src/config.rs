@@ -2,10 +2,20 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{graphics::model::Model, simulation::Simulation};
+use crate::{
+    graphics::{headless::RenderConfig, model::Model, ViewerConfig},
+    output_adapter::plot_adapter::PlotConfig,
+    simulation::Simulation,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config<const N: usize> {
     pub simulation: Simulation<N>,
     pub models: HashMap<String, Model>,
+    #[serde(default)]
+    pub plot: PlotConfig,
+    #[serde(default)]
+    pub render: RenderConfig,
+    #[serde(default)]
+    pub viewer: ViewerConfig,
 }
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+use crate::force::{ForceMap, ForceVector};
+use crate::math::{Distance, Vector};
+use crate::simulation::BodyMap;
+
+/// Tolerance on the relative error in the length of rods
+const ROD_TOLERANCE: f32 = 1e-6;
+/// Most passes over the rods to bring their lengths within tolerance
+const MAX_ROD_ITERATIONS: usize = 100;
+
+/// A physical link between the centres of two bodies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Connection {
+    /// Hookean spring pulling or pushing the bodies towards `rest_length`
+    /// apart with `stiffness` in newtons per metre, and resisting their
+    /// relative motion along it with `damping` in newton seconds per
+    /// metre. A `tether` only pulls, going slack when shorter than its
+    /// rest length.
+    Spring {
+        bodies: [String; 2],
+        rest_length: f32,
+        stiffness: f32,
+        #[serde(default)]
+        damping: f32,
+        #[serde(default)]
+        tether: bool,
+    },
+    /// Massless rigid rod holding the bodies exactly `length` apart
+    Rod { bodies: [String; 2], length: f32 },
+}
+
+impl Connection {
+    pub fn bodies(&self) -> &[String; 2] {
+        match self {
+            Self::Spring { bodies, .. } | Self::Rod { bodies, .. } => bodies,
+        }
+    }
+}
+
+/// Adds the forces of the springs on the bodies at each of their ends
+pub fn add_spring_forces<const N: usize>(
+    connections: &[Connection],
+    body_map: &BodyMap<N>,
+    force_map: &mut ForceMap<N>,
+) {
+    for connection in connections {
+        let (bodies, rest_length, stiffness, damping, tether) = match connection {
+            Connection::Spring {
+                bodies,
+                rest_length,
+                stiffness,
+                damping,
+                tether,
+            } => (bodies, *rest_length, *stiffness, *damping, *tether),
+            Connection::Rod { .. } => continue,
+        };
+        let (a, b) = match (body_map.get(&bodies[0]), body_map.get(&bodies[1])) {
+            (Some(a), Some(b)) => (a, b),
+            _ => continue,
+        };
+        let length = a.position.distance(&b.position);
+        if length == 0.0 || (tether && length <= rest_length) {
+            continue;
+        }
+        let direction = a.position.direction(&b.position);
+        let extension_rate = (&b.velocity - &a.velocity).dot(&direction);
+        // Tension, pulling the ends together when positive
+        let tension = stiffness * (length - rest_length) + damping * extension_rate;
+        for (on, from, sign) in [(a, b, 1.0), (b, a, -1.0)] {
+            force_map
+                .entry(on.label.clone())
+                .or_default()
+                .push(ForceVector {
+                    label: format!("spring_{}", from.label),
+                    v: (sign * tension) * &direction,
+                });
+        }
+    }
+}
+
+/// Moves the bodies at the ends of rods back to the rods' lengths after an
+/// unconstrained step from `previous` over `t_step`, using SHAKE along the
+/// rods' previous directions. The velocities are corrected to match, and
+/// then with RATTLE so the ends don't move along the rods.
+pub fn apply_rods<const N: usize>(
+    connections: &[Connection],
+    previous: &BodyMap<N>,
+    body_map: &mut BodyMap<N>,
+    t_step: f32,
+) {
+    let rods: Vec<(&[String; 2], f32, Vector<N>)> = connections
+        .iter()
+        .filter_map(|connection| match connection {
+            Connection::Rod { bodies, length } => {
+                let a = previous.get(&bodies[0])?;
+                let b = previous.get(&bodies[1])?;
+                Some((bodies, *length, &b.position - &a.position))
+            }
+            Connection::Spring { .. } => None,
+        })
+        .collect();
+    if rods.is_empty() {
+        return;
+    }
+
+    for _ in 0..MAX_ROD_ITERATIONS {
+        let mut converged = true;
+        for (bodies, length, previous_r) in &rods {
+            let (a, b) = (&body_map[&bodies[0]], &body_map[&bodies[1]]);
+            let r = &b.position - &a.position;
+            let error = length.powi(2) - r.dot(&r);
+            if error.abs() <= 2.0 * ROD_TOLERANCE * length.powi(2) {
+                continue;
+            }
+            converged = false;
+            let (inverse_a, inverse_b) = (1.0 / a.mass, 1.0 / b.mass);
+            let g = error / (2.0 * (inverse_a + inverse_b) * r.dot(previous_r));
+            let (shift_a, shift_b) = ((-g * inverse_a) * previous_r, (g * inverse_b) * previous_r);
+            for (label, shift) in [(&bodies[0], shift_a), (&bodies[1], shift_b)] {
+                let body = body_map.get_mut(label).unwrap();
+                body.position = &body.position + &shift;
+                body.velocity = &body.velocity + &((1.0 / t_step) * &shift);
+            }
+        }
+        if converged {
+            break;
+        }
+    }
+
+    for (bodies, _, _) in &rods {
+        let (a, b) = (&body_map[&bodies[0]], &body_map[&bodies[1]]);
+        let r = &b.position - &a.position;
+        let (inverse_a, inverse_b) = (1.0 / a.mass, 1.0 / b.mass);
+        let k = (&b.velocity - &a.velocity).dot(&r) / ((inverse_a + inverse_b) * r.dot(&r));
+        for (label, impulse) in [(&bodies[0], k * &r), (&bodies[1], -k * &r)] {
+            let body = body_map.get_mut(label).unwrap();
+            let inverse_mass = 1.0 / body.mass;
+            body.velocity = &body.velocity + &(inverse_mass * &impulse);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector3;
+    use crate::simulation::{Body, Run, Simulation};
+    use approx::assert_abs_diff_eq;
+
+    fn simulation(connection: Connection, velocity: Vector3) -> Simulation<3> {
+        let mut simulation =
+            Simulation::new(Some(0.0), Some(10.0), Some(1e-3)).with_connections(vec![connection]);
+        for (label, x, v) in [("a", -1.0, Vector3::default()), ("b", 1.0, velocity)] {
+            simulation.add_body(Body::new(
+                String::from(label),
+                1.0,
+                0.1,
+                Vector3::new(x, 0.0, 0.0),
+                v,
+                Default::default(),
+            ));
+        }
+        simulation
+    }
+
+    #[test]
+    fn springs_oscillate_about_their_rest_length() {
+        let spring = Connection::Spring {
+            bodies: [String::from("a"), String::from("b")],
+            rest_length: 1.5,
+            stiffness: 2.0,
+            damping: 0.0,
+            tether: false,
+        };
+        // Reduced mass 1/2, so ω = 2 and half a period from 0.5 stretched
+        // to 0.5 compressed takes π/2
+        let simulation = simulation(spring, Vector3::default());
+        let steps = (std::f32::consts::FRAC_PI_2 / 1e-3) as usize;
+        let body_map = Run::from(&simulation).nth(steps).unwrap().body_map;
+        let length = body_map["a"].position.distance(&body_map["b"].position);
+        assert_abs_diff_eq!(length, 1.0, epsilon = 0.01);
+    }
+
+    #[test]
+    fn rods_hold_bodies_at_their_length() {
+        let rod = Connection::Rod {
+            bodies: [String::from("a"), String::from("b")],
+            length: 2.0,
+        };
+        let simulation = simulation(rod, Vector3::new(0.0, 0.0, 1.0));
+        for step in Run::from(&simulation) {
+            let (a, b) = (&step.body_map["a"], &step.body_map["b"]);
+            assert_abs_diff_eq!(a.position.distance(&b.position), 2.0, epsilon = 1e-4);
+            let relative_velocity = &b.velocity - &a.velocity;
+            let along = relative_velocity.dot(&a.position.direction(&b.position));
+            assert_abs_diff_eq!(along, 0.0, epsilon = 1e-4);
+        }
+    }
+
+    #[test]
+    fn connections_to_unknown_bodies_are_rejected() {
+        let yaml = |other: &str| {
+            format!(
+                "t_start: 0.0
+t_step: 1.0
+bodies:
+  - {{label: a, mass: 1.0, diameter: 0.1, position: [0.0, 0.0, 0.0], velocity: [0.0, 0.0, 0.0]}}
+  - {{label: b, mass: 1.0, diameter: 0.1, position: [1.0, 0.0, 0.0], velocity: [0.0, 0.0, 0.0]}}
+connections:
+  - rod: {{bodies: [a, {}], length: 1.0}}
+",
+                other
+            )
+        };
+        assert!(serde_yaml::from_str::<Simulation<3>>(&yaml("b")).is_ok());
+        let error = serde_yaml::from_str::<Simulation<3>>(&yaml("c")).unwrap_err();
+        assert!(error.to_string().contains("unknown body c"));
+    }
+}
@@ -1,12 +1,18 @@
-use crate::math::Distance;
+use crate::math::{Distance, Vector, Vector3};
 use crate::simulation::{Body, PositionVector};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-const G: f32 = 6.67430e-11;
+pub const G: f32 = 6.67430e-11;
+/// Speed of light in metres per second
+pub const C: f32 = 299_792_458.0;
+/// Coulomb constant in newton square metres per square coulomb
+pub const K: f32 = 8.987_552e9;
+/// Drag coefficient of bodies that don't give one, typical of satellites
+const DRAG_COEFFICIENT: f32 = 2.2;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForceVector<const N: usize> {
     pub label: String,
     pub v: PositionVector<N>,
@@ -18,10 +24,57 @@ impl<const N: usize> ForceVector<N> {
     }
 }
 
+/// A labelled torque on a body, always in three dimensions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorqueVector {
+    pub label: String,
+    pub v: Vector3,
+}
+
 pub trait Force {
     /// Returns the vector of the force calculated between
     /// two objects of type T.
     fn calculate<'a, const N: usize>(&self, on: &'a Body<N>, from: &'a Body<N>) -> ForceVector<N>;
+
+    /// Returns the torque on the spin of `on` from its interaction with
+    /// `from`, for forces that have one.
+    fn torque<'a, const N: usize>(
+        &self,
+        _on: &'a Body<N>,
+        _from: &'a Body<N>,
+    ) -> Option<TorqueVector> {
+        None
+    }
+
+    /// Whether the force acts between the two bodies at all
+    fn acts_between<const N: usize>(&self, _a: &Body<N>, _b: &Body<N>) -> bool {
+        true
+    }
+
+    /// Adds the force, and any torque, on each body from every other body
+    /// it acts between.
+    fn add_forces<const N: usize>(
+        &self,
+        bodies: &[&Body<N>],
+        force_map: &mut ForceMap<N>,
+        torque_map: &mut TorqueMap,
+    ) {
+        for body_pair in bodies.iter().combinations(2) {
+            let (b1, b2) = (*body_pair[0], *body_pair[1]);
+            if !self.acts_between(b1, b2) {
+                continue;
+            }
+            for (on, from) in [(b1, b2), (b2, b1)] {
+                force_map
+                    .entry(on.label.clone())
+                    .or_default()
+                    .push(self.calculate(on, from));
+                if let Some(torque) = self.torque(on, from) {
+                    torque_map.entry(on.label.clone()).or_default().push(torque);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -34,36 +87,571 @@ impl Gravity {
         Gravity { g: g.unwrap_or(G) }
     }
 
-    pub fn forces_from_bodies<const N: usize>(&self, bodies: &Vec<&Body<N>>) -> ForceMap<N> {
-        let mut force_map = ForceMap::new();
-        for body_pair in bodies.iter().combinations(2) {
-            let (b1, b2) = (body_pair[0], body_pair[1]);
-
-            force_map
-                .entry(b1.label.clone())
-                .or_insert(vec![])
-                .push(self.calculate(b1, b2));
-
-            force_map
-                .entry(b2.label.clone())
-                .or_insert(vec![])
-                .push(self.calculate(b2, b1));
+    /// Acceleration of a body at `position` from the zonal harmonics of
+    /// `body`'s gravity field, on top of its pull as a point mass
+    fn zonal_acceleration<const N: usize>(&self, body: &Body<N>, position: &Vector3) -> Vector3 {
+        let field = match body.gravity_field {
+            Some(field) => field,
+            None => return Vector3::default(),
+        };
+        let r = position - &body.position.resized::<3>();
+        let distance = r.magnitude();
+        let r_hat = r.normalize();
+        let pole = body
+            .attitude
+            .orientation
+            .rotate(&Vector3::new(0.0, 1.0, 0.0));
+        let u = r_hat.dot(&pole);
+        let ratio = field.reference_radius.unwrap_or(body.diameter / 2.0) / distance;
+        // μ / r² before scaling, so large masses don't overflow
+        let scale = self.g * body.mass / distance.powi(2);
+        let mut acceleration = Vector3::default();
+        for (n, j) in [(2, field.j2), (3, field.j3), (4, field.j4)] {
+            if j == 0.0 {
+                continue;
+            }
+            // -∇ of the potential μ Jn Rⁿ Pn(u) / rⁿ⁺¹
+            let (p, dp) = legendre(n, u);
+            let radial = (n as f32 + 1.0) * p + u * dp;
+            let term = &(radial * &r_hat) - &(dp * &pole);
+            acceleration = &acceleration + &((scale * j * ratio.powi(n)) * &term);
         }
-        force_map
+        acceleration
+    }
+}
+
+/// Legendre polynomial of degree `n` and its derivative at `u`
+fn legendre(n: i32, u: f32) -> (f32, f32) {
+    let u2 = u * u;
+    match n {
+        2 => ((3.0 * u2 - 1.0) / 2.0, 3.0 * u),
+        3 => ((5.0 * u2 - 3.0) * u / 2.0, (15.0 * u2 - 3.0) / 2.0),
+        4 => (
+            ((35.0 * u2 - 30.0) * u2 + 3.0) / 8.0,
+            (35.0 * u2 - 15.0) * u / 2.0,
+        ),
+        _ => unreachable!(
+            "only J2 to J4 zonal harmonics are modelled, not degree {}",
+            n
+        ),
     }
 }
 
 impl Force for Gravity {
     fn calculate<'a, const N: usize>(&self, on: &'a Body<N>, from: &'a Body<N>) -> ForceVector<N> {
         let distance = on.position.distance(&from.position);
-        let magnitude = self.g * on.mass * from.mass / distance.powi(2);
+        // The product of two large masses overflows f32, so the
+        // acceleration is found first
+        let magnitude = self.g * from.mass / distance.powi(2) * on.mass;
+        // The field of `from` pulls on `on`, and the field of `on` pulls
+        // back equally on `from`
+        let zonal = &(on.mass * &self.zonal_acceleration(from, &on.position.resized::<3>()))
+            - &(from.mass * &self.zonal_acceleration(on, &from.position.resized::<3>()));
 
         let on_force_name = format!("gravity_{}", from.label);
         ForceVector {
             label: on_force_name,
-            v: magnitude * &on.position.direction(&from.position),
+            v: &(magnitude * &on.position.direction(&from.position)) + &zonal.resized::<N>(),
         }
     }
+
+    fn torque<'a, const N: usize>(
+        &self,
+        on: &'a Body<N>,
+        from: &'a Body<N>,
+    ) -> Option<TorqueVector> {
+        on.gravity_field?;
+        // The pull of the field on `from` isn't central, so its reaction
+        // twists the spin axis of `on`
+        let r = (&from.position - &on.position).resized::<3>();
+        let force = from.mass * &self.zonal_acceleration(on, &from.position.resized::<3>());
+        Some(TorqueVector {
+            label: format!("gravity_{}", from.label),
+            v: -1.0 * &r.cross(&force),
+        })
+    }
+}
+
+/// Electrostatic force between charged bodies, repelling like charges and
+/// attracting opposite ones
+#[derive(Debug)]
+pub struct Coulomb {
+    k: f32,
+}
+
+impl Coulomb {
+    pub fn new(k: Option<f32>) -> Self {
+        Coulomb { k: k.unwrap_or(K) }
+    }
+}
+
+impl Force for Coulomb {
+    fn calculate<'a, const N: usize>(&self, on: &'a Body<N>, from: &'a Body<N>) -> ForceVector<N> {
+        let distance = on.position.distance(&from.position);
+        let charges = on.charge.unwrap_or_default() * from.charge.unwrap_or_default();
+        let magnitude = self.k * charges / distance.powi(2);
+
+        ForceVector {
+            label: format!("coulomb_{}", from.label),
+            v: magnitude * &from.position.direction(&on.position),
+        }
+    }
+
+    fn acts_between<const N: usize>(&self, a: &Body<N>, b: &Body<N>) -> bool {
+        a.charge.is_some() && b.charge.is_some()
+    }
+}
+
+/// Force of uniform electric and magnetic fields on charged bodies,
+/// q(E + v × B)
+#[derive(Debug)]
+pub struct Lorentz {
+    electric: Vector3,
+    magnetic: Vector3,
+}
+
+impl Lorentz {
+    pub fn new(electric: Vector3, magnetic: Vector3) -> Self {
+        Lorentz { electric, magnetic }
+    }
+
+    /// Adds the force on each charged body
+    pub fn add_forces<const N: usize>(&self, bodies: &[&Body<N>], force_map: &mut ForceMap<N>) {
+        if self.electric == Vector3::default() && self.magnetic == Vector3::default() {
+            return;
+        }
+        for body in bodies {
+            let charge = match body.charge {
+                Some(charge) => charge,
+                None => continue,
+            };
+            let v = body.velocity.resized::<3>();
+            let force = charge * &(&self.electric + &v.cross(&self.magnetic));
+            force_map
+                .entry(body.label.clone())
+                .or_default()
+                .push(ForceVector {
+                    label: String::from("lorentz"),
+                    v: force.resized::<N>(),
+                });
+        }
+    }
+}
+
+/// First post-Newtonian correction to gravity, in the approximation where
+/// each body moves in the Schwarzschild field of the other as a test
+/// particle. This is accurate when one body dominates, like the Sun and a
+/// planet, and gives the relativistic precession of the orbit's
+/// periapsis. The speed of light `c` must be in the same units as `g`.
+/// Around the Sun the correction is a few parts in 10⁸ of gravity, below
+/// single precision, so its effect only shows in scaled units. Mercury's
+/// 43 arcseconds a century, 5 × 10⁻⁷ radians an orbit, is lost in the
+/// integrator's own precession and can't be reproduced in SI units.
+#[derive(Debug)]
+pub struct Relativity {
+    g: f32,
+    c: f32,
+}
+
+impl Relativity {
+    pub fn new(g: Option<f32>, c: Option<f32>) -> Self {
+        Relativity {
+            g: g.unwrap_or(G),
+            c: c.unwrap_or(C),
+        }
+    }
+}
+
+impl Force for Relativity {
+    fn calculate<'a, const N: usize>(&self, on: &'a Body<N>, from: &'a Body<N>) -> ForceVector<N> {
+        let r = &on.position - &from.position;
+        let v = &on.velocity - &from.velocity;
+        let distance = r.magnitude();
+        // a = μ/(c²r³) [(4μ/r - v²) r + 4(r·v) v], which is computed in
+        // f64 as the cube of the distance overflows f32
+        let mu = self.g as f64 * from.mass as f64;
+        let scale = mu / (self.c as f64).powi(2) / (distance as f64).powi(3);
+        let radial = 4.0 * mu / distance as f64 - v.dot(&v) as f64;
+        let tangential = 4.0 * r.dot(&v) as f64;
+        let acceleration = &((scale * radial) as f32 * &r) + &((scale * tangential) as f32 * &v);
+        ForceVector {
+            label: format!("relativity_{}", from.label),
+            v: on.mass * &acceleration,
+        }
+    }
+}
+
+/// Tides raised on bodies with a Love number `k2` by the other bodies,
+/// using Mignard's constant time lag model. The bulge lags its tide
+/// raising body by the body's `tidal_lag`, which slows or speeds up its
+/// spin and moves the other body's orbit outwards or inwards, conserving
+/// angular momentum.
+#[derive(Debug)]
+pub struct Tides {
+    g: f32,
+}
+
+impl Tides {
+    pub fn new(g: Option<f32>) -> Self {
+        Tides { g: g.unwrap_or(G) }
+    }
+
+    /// Force on `raiser` from the tidal bulge it raises on `body`
+    fn bulge_force<const N: usize>(&self, body: &Body<N>, raiser: &Body<N>) -> Vector3 {
+        let k2 = match body.k2 {
+            Some(k2) => k2,
+            None => return Vector3::default(),
+        };
+        let r = (&raiser.position - &body.position).resized::<3>();
+        let v = (&raiser.velocity - &body.velocity).resized::<3>();
+        let distance = r.magnitude();
+        let radius = body.diameter / 2.0;
+        // Masses squared overflow f32, so the magnitude is computed in f64
+        let coefficient = 3.0
+            * k2 as f64
+            * self.g as f64
+            * (raiser.mass as f64).powi(2)
+            * (radius as f64).powi(5)
+            / (distance as f64).powi(8);
+        let lag = body.tidal_lag.unwrap_or_default();
+        let spin = body.attitude.angular_velocity;
+        let delayed = &(&((2.0 * r.dot(&v) / distance.powi(2)) * &r) + &r.cross(&spin)) + &v;
+        -(coefficient as f32) * &(&r + &(lag * &delayed))
+    }
+}
+
+impl Force for Tides {
+    fn calculate<'a, const N: usize>(&self, on: &'a Body<N>, from: &'a Body<N>) -> ForceVector<N> {
+        // The tide `on` raises on `from` pulls on it, and the tide `from`
+        // raises on `on` pulls it back
+        let v = &self.bulge_force(from, on) - &self.bulge_force(on, from);
+        ForceVector {
+            label: format!("tides_{}", from.label),
+            v: v.resized::<N>(),
+        }
+    }
+
+    fn torque<'a, const N: usize>(
+        &self,
+        on: &'a Body<N>,
+        from: &'a Body<N>,
+    ) -> Option<TorqueVector> {
+        on.k2?;
+        // The bulge's pull on `from` changes its orbital angular momentum
+        // about `on`, which is taken from the spin of `on`
+        let r = (&from.position - &on.position).resized::<3>();
+        let force = self.bulge_force(on, from);
+        Some(TorqueVector {
+            label: format!("tides_{}", from.label),
+            v: -1.0 * &r.cross(&force),
+        })
+    }
+
+    fn acts_between<const N: usize>(&self, a: &Body<N>, b: &Body<N>) -> bool {
+        a.k2.is_some() || b.k2.is_some()
+    }
+}
+
+/// Drag on bodies moving through the atmosphere of another body, which
+/// rotates with it. The cross-section of the dragged body is a circle of
+/// its diameter.
+#[derive(Debug)]
+pub struct Drag;
+
+impl Drag {
+    /// Force on `body` from the atmosphere of `primary`
+    fn drag_force<const N: usize>(&self, body: &Body<N>, primary: &Body<N>) -> Vector3 {
+        let atmosphere = match &primary.atmosphere {
+            Some(atmosphere) => atmosphere,
+            None => return Vector3::default(),
+        };
+        let r = (&body.position - &primary.position).resized::<3>();
+        let density = atmosphere.density(r.magnitude() - primary.diameter / 2.0);
+        if density == 0.0 {
+            return Vector3::default();
+        }
+        let wind = primary.attitude.angular_velocity.cross(&r);
+        let v = &(&body.velocity - &primary.velocity).resized::<3>() - &wind;
+        let drag_coefficient = body.drag_coefficient.unwrap_or(DRAG_COEFFICIENT);
+        let area = std::f32::consts::PI * (body.diameter / 2.0).powi(2);
+        (-0.5 * density * drag_coefficient * area * v.magnitude()) * &v
+    }
+}
+
+impl Force for Drag {
+    fn calculate<'a, const N: usize>(&self, on: &'a Body<N>, from: &'a Body<N>) -> ForceVector<N> {
+        let v = &self.drag_force(on, from) - &self.drag_force(from, on);
+        ForceVector {
+            label: format!("drag_{}", from.label),
+            v: v.resized::<N>(),
+        }
+    }
+
+    fn torque<'a, const N: usize>(
+        &self,
+        on: &'a Body<N>,
+        from: &'a Body<N>,
+    ) -> Option<TorqueVector> {
+        on.atmosphere.as_ref()?;
+        // The atmosphere is carried round by the body, so the momentum it
+        // takes from `from` turns up in the body's spin
+        let r = (&from.position - &on.position).resized::<3>();
+        let force = self.drag_force(from, on);
+        Some(TorqueVector {
+            label: format!("drag_{}", from.label),
+            v: -1.0 * &r.cross(&force),
+        })
+    }
+
+    fn acts_between<const N: usize>(&self, a: &Body<N>, b: &Body<N>) -> bool {
+        a.atmosphere.is_some() || b.atmosphere.is_some()
+    }
+}
+
+/// Pressure of the light of luminous bodies on the bodies it falls on,
+/// including the Poynting–Robertson drag from the aberration and Doppler
+/// shift of the light seen by a moving body, which makes dust spiral in
+/// towards its star. The light is taken to be absorbed or reflected back
+/// along its path, and its source doesn't recoil.
+#[derive(Debug)]
+pub struct RadiationPressure {
+    c: f32,
+}
+
+impl RadiationPressure {
+    pub fn new(c: Option<f32>) -> Self {
+        RadiationPressure { c: c.unwrap_or(C) }
+    }
+
+    /// Force on `body` from the light of `source`
+    fn radiation_force<const N: usize>(&self, body: &Body<N>, source: &Body<N>) -> Vector<N> {
+        let luminosity = match source.luminosity {
+            Some(luminosity) => luminosity,
+            None => return Vector::default(),
+        };
+        let r = &body.position - &source.position;
+        let r_hat = r.normalize();
+        let v = &body.velocity - &source.velocity;
+        let flux = luminosity / (4.0 * std::f32::consts::PI * r.dot(&r));
+        let area = std::f32::consts::PI * (body.diameter / 2.0).powi(2);
+        let efficiency = 1.0 + body.reflectivity.unwrap_or_default();
+        // F = (Φ A Q / c) [(1 - ṙ/c) r̂ - v/c]
+        let radial = 1.0 - r_hat.dot(&v) / self.c;
+        let pressure = flux * area * efficiency / self.c;
+        pressure * &(&(radial * &r_hat) - &((1.0 / self.c) * &v))
+    }
+}
+
+impl Force for RadiationPressure {
+    fn calculate<'a, const N: usize>(&self, on: &'a Body<N>, from: &'a Body<N>) -> ForceVector<N> {
+        ForceVector {
+            label: format!("radiation_{}", from.label),
+            v: self.radiation_force(on, from),
+        }
+    }
+
+    fn acts_between<const N: usize>(&self, a: &Body<N>, b: &Body<N>) -> bool {
+        a.luminosity.is_some() || b.luminosity.is_some()
+    }
 }
 
 pub type ForceMap<const N: usize> = HashMap<String, Vec<ForceVector<N>>>;
+pub type TorqueMap = HashMap<String, Vec<TorqueVector>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Atmosphere, GravityField, SpinCharacteristics};
+    use approx::assert_abs_diff_eq;
+
+    /// A planet at the origin spinning faster than a moon orbits it
+    fn planet_and_moon(tidal_lag: f32) -> (Body<3>, Body<3>) {
+        let spin = SpinCharacteristics {
+            velocity: 5.0,
+            ..Default::default()
+        };
+        let mut planet = Body::new(
+            String::from("planet"),
+            1.0,
+            0.2,
+            Vector3::default(),
+            Vector3::default(),
+            spin,
+        );
+        planet.k2 = Some(0.3);
+        planet.tidal_lag = Some(tidal_lag);
+        let moon = Body::new(
+            String::from("moon"),
+            0.01,
+            0.05,
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Default::default(),
+        );
+        (planet, moon)
+    }
+
+    #[test]
+    fn oblate_bodies_pull_harder_at_the_equator_than_the_poles() {
+        let gravity = Gravity::new(Some(1.0));
+        let mut planet = Body::new(
+            String::from("planet"),
+            1.0,
+            1.0,
+            Vector3::default(),
+            Vector3::default(),
+            Default::default(),
+        );
+        let satellite_at = |position| {
+            Body::new(
+                String::from("satellite"),
+                0.001,
+                0.0,
+                position,
+                Vector3::default(),
+                Default::default(),
+            )
+        };
+        let equatorial = satellite_at(Vector3::new(1.0, 0.0, 0.0));
+        let polar = satellite_at(Vector3::new(0.0, 1.0, 0.0));
+        let point_mass = gravity.calculate(&equatorial, &planet).v;
+
+        planet.gravity_field = Some(GravityField {
+            j2: 0.1,
+            ..Default::default()
+        });
+        // -3/2 J2 (R/r)² inwards at the equator and 3 J2 (R/r)² outwards at
+        // the poles, relative to μ/r²
+        let force = gravity.calculate(&equatorial, &planet).v;
+        assert_abs_diff_eq!(force.x() / point_mass.x(), 1.0375, epsilon = 1e-6);
+        let force = gravity.calculate(&polar, &planet).v;
+        assert_abs_diff_eq!(force.y() / -point_mass.x(), -0.925, epsilon = 1e-6);
+        let reaction = gravity.calculate(&planet, &polar).v;
+        assert_abs_diff_eq!(reaction.y(), -force.y(), epsilon = 1e-9);
+        assert!(gravity.torque(&planet, &polar).is_some());
+        assert!(gravity.torque(&polar, &planet).is_none());
+    }
+
+    #[test]
+    fn drag_slows_bodies_relative_to_the_rotating_atmosphere() {
+        let spin = SpinCharacteristics {
+            velocity: 0.5,
+            ..Default::default()
+        };
+        let mut planet = Body::new(
+            String::from("planet"),
+            1.0,
+            2.0,
+            Vector3::default(),
+            Vector3::default(),
+            spin,
+        );
+        planet.atmosphere = Some(Atmosphere::Exponential {
+            density: 1.0,
+            scale_height: 1.0,
+            ceiling: None,
+        });
+        // Spinning about y carries the air at (2, 0, 0) towards -z
+        let at_rest_in_the_air = Body::new(
+            String::from("satellite"),
+            0.001,
+            0.1,
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Default::default(),
+        );
+        let drag = Drag.calculate(&at_rest_in_the_air, &planet);
+        assert_eq!(drag.v.magnitude(), 0.0);
+
+        let satellite = Body {
+            velocity: Vector3::new(0.0, 0.0, -3.0),
+            ..at_rest_in_the_air
+        };
+        let drag = Drag.calculate(&satellite, &planet);
+        // ½ ρ Cd A v² at one scale height up
+        let expected = 0.5 / std::f32::consts::E * 2.2 * std::f32::consts::PI * 0.0025 * 4.0;
+        assert_abs_diff_eq!(drag.v.z(), expected, epsilon = 1e-6);
+        assert_eq!(Drag.calculate(&planet, &satellite).v, -1.0 * &drag.v);
+        // Overtaking the air pushes it round faster, spinning the planet up
+        assert!(Drag.torque(&planet, &satellite).unwrap().v.y() > 0.0);
+    }
+
+    #[test]
+    fn light_pushes_dust_away_and_drags_on_its_orbit() {
+        let radiation = RadiationPressure::new(Some(10.0));
+        let mut star = Body::new(
+            String::from("star"),
+            1.0,
+            0.1,
+            Vector3::default(),
+            Vector3::default(),
+            Default::default(),
+        );
+        star.luminosity = Some(4.0 * std::f32::consts::PI);
+        let mut dust = Body::new(
+            String::from("dust"),
+            1e-6,
+            2.0,
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::default(),
+            Default::default(),
+        );
+        dust.reflectivity = Some(0.5);
+        // L / (4π r²) A (1 + reflectivity) / c, away from the star
+        let force = radiation.calculate(&dust, &star);
+        assert_abs_diff_eq!(force.v.x(), 0.25 * std::f32::consts::PI * 1.5 / 10.0);
+        assert_eq!(radiation.calculate(&star, &dust).v.magnitude(), 0.0);
+
+        let orbiting = Body {
+            velocity: Vector3::new(0.0, 0.0, -1.0),
+            ..dust
+        };
+        let force = radiation.calculate(&orbiting, &star);
+        assert!(force.v.dot(&orbiting.velocity) < 0.0);
+    }
+
+    #[test]
+    fn like_charges_repel_and_opposite_charges_attract() {
+        let coulomb = Coulomb::new(Some(1.0));
+        let charged_at = |x, charge| {
+            let mut body = Body::new(
+                String::from("particle"),
+                1.0,
+                0.0,
+                Vector3::new(x, 0.0, 0.0),
+                Vector3::default(),
+                Default::default(),
+            );
+            body.charge = Some(charge);
+            body
+        };
+        let (a, b) = (charged_at(0.0, 2.0), charged_at(2.0, 3.0));
+        assert_eq!(coulomb.calculate(&b, &a).v, Vector3::new(1.5, 0.0, 0.0));
+        let c = charged_at(2.0, -3.0);
+        assert_eq!(coulomb.calculate(&c, &a).v, Vector3::new(-1.5, 0.0, 0.0));
+        assert!(!coulomb.acts_between(&a, &Body { charge: None, ..c }));
+    }
+
+    #[test]
+    fn a_lagging_bulge_speeds_up_the_moon_and_slows_the_spin() {
+        let tides = Tides::new(Some(1.0));
+        let (planet, moon) = planet_and_moon(0.1);
+        let force = tides.calculate(&moon, &planet);
+        assert!(force.v.dot(&moon.velocity) > 0.0);
+        let torque = tides.torque(&planet, &moon).unwrap();
+        assert!(torque.v.dot(&planet.attitude.angular_velocity) < 0.0);
+        // Rigid bodies feel no torque, and the forces on each are opposite
+        assert!(tides.torque(&moon, &planet).is_none());
+        let reaction = tides.calculate(&planet, &moon);
+        assert_eq!(reaction.v, -1.0 * &force.v);
+    }
+
+    #[test]
+    fn an_instant_bulge_exerts_no_torque() {
+        let tides = Tides::new(Some(1.0));
+        let (planet, moon) = planet_and_moon(0.0);
+        let torque = tides.torque(&planet, &moon).unwrap();
+        assert_eq!(torque.v.magnitude(), 0.0);
+        assert!(!tides.acts_between(&moon, &moon));
+    }
+}
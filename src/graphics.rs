@@ -1,20 +1,96 @@
-use glam::{vec3, Mat4};
+use glam::{vec2, vec3, Mat4, Quat, Vec2, Vec3};
 use miniquad::{
-    conf::Conf, BufferLayout, Comparison, Context, CullFace, EventHandler, KeyCode, MouseButton,
-    PassAction, Pipeline, PipelineParams, Shader, ShaderMeta, UniformBlockLayout, UniformDesc,
-    UniformType, VertexAttribute, VertexFormat, VertexStep,
+    conf::Conf, BlendFactor, BlendState, BlendValue, BufferLayout, Comparison, Context, CullFace,
+    Equation, EventHandler, KeyCode, KeyMods, MouseButton, PassAction, Pipeline, PipelineParams,
+    Shader, ShaderMeta, UniformBlockLayout, UniformDesc, UniformType, VertexAttribute,
+    VertexFormat, VertexStep,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::min,
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
 };
-use std::{cmp::min, collections::HashMap, path::PathBuf};
 
 use crate::{
-    math::Vector3,
+    connection::Connection,
+    force::ForceVector,
+    math::{Quaternion, Vector3},
     simulation::{Body, OwningRun, Simulation},
+    trajectory::Trajectory,
 };
 
+pub mod camera;
+pub mod font;
+pub mod headless;
+pub mod hud;
+pub mod lighting;
+pub mod lines;
 pub mod model;
+pub mod obj;
+pub mod picking;
+pub mod points;
+pub mod replay;
+pub mod scale;
+pub mod skybox;
+pub mod text;
 pub mod trackball;
-use self::model::{Model, Uniforms};
+pub mod vectors;
+use self::camera::{Camera, CameraConfig, CameraMode};
+use self::hud::{Hud, HudUnits};
+use self::lighting::{SceneLighting, MAX_LIGHTS, MAX_OCCLUDERS};
+use self::lines::LineRenderer;
+use self::model::{Model, Shape, Uniforms};
+use self::points::PointRenderer;
+use self::replay::Replay;
+use self::scale::{DistanceMapping, ScaleConfig, SceneScale};
+use self::skybox::Skybox;
+use self::text::TextRenderer;
 use self::trackball::Trackball;
+use self::vectors::VectorOverlay;
+
+/// Settings for the graphical viewer
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewerConfig {
+    pub camera: CameraConfig,
+    pub scale: ScaleConfig,
+    /// Whether bodies cast shadows on each other
+    pub shadows: bool,
+    /// Whether to draw the recent path of each body
+    pub trails: bool,
+    /// Whether to draw the path each body is predicted to take
+    pub prediction: bool,
+    /// Number of simulation steps to predict ahead
+    pub prediction_steps: usize,
+    /// UTC date and time at t = 0 as `YYYY-MM-DDTHH:MM:SS`, used to show
+    /// the simulated date
+    pub epoch: Option<String>,
+    /// Equirectangular image of the sky drawn behind the scene, relative to
+    /// the config root
+    pub skybox: Option<PathBuf>,
+    /// Whether to draw a reference grid in the xz plane, and the axes
+    pub grid: bool,
+    /// Units of the details shown for the selected body
+    pub units: HudUnits,
+}
+
+impl Default for ViewerConfig {
+    fn default() -> Self {
+        Self {
+            camera: CameraConfig::default(),
+            scale: ScaleConfig::default(),
+            shadows: true,
+            trails: true,
+            prediction: false,
+            prediction_steps: 1000,
+            epoch: None,
+            skybox: None,
+            grid: false,
+            units: HudUnits::default(),
+        }
+    }
+}
 
 pub fn new_conf() -> Conf {
     Conf {
@@ -26,34 +102,151 @@ pub fn new_conf() -> Conf {
 #[derive(Default)]
 pub struct BodyState {
     pos: Vector3,
-    rot: f32,
+    velocity: Vector3,
+    mass: f32,
+    orientation: Quaternion,
     diameter: f32,
-    tilt: f32,
+    /// Forces acting on the body in the last step
+    forces: Vec<ForceVector<3>>,
 }
 impl From<&Body<3>> for BodyState {
     fn from(body: &Body<3>) -> Self {
         BodyState {
             pos: body.position,
-            rot: body.spin.angle,
+            velocity: body.velocity,
+            mass: body.mass,
+            orientation: body.attitude.orientation,
             diameter: body.diameter,
-            tilt: body.spin.tilt,
+            forces: body.forces.clone(),
         }
     }
 }
 
+impl BodyState {
+    /// Transform from the unit sphere to the body's scaled position, size
+    /// and orientation in the scene.
+    pub fn model_matrix(&self, scale: &SceneScale) -> Mat4 {
+        let q = &self.orientation;
+        Mat4::from_scale_rotation_translation(
+            scale.size(self.diameter) * Vec3::ONE,
+            Quat::from_xyzw(q.x, q.y, q.z, q.w),
+            scale.position(&self.pos),
+        )
+    }
+}
+
 pub type BodyStateMap = HashMap<String, BodyState>;
 
+pub(crate) fn to_vec3(v: &Vector3) -> Vec3 {
+    vec3(v.x(), v.y(), v.z())
+}
+
+pub const LIGHT_COLOR: Vec3 = Vec3::ONE;
+/// Position of the light in the scene when no model is emissive
+pub const LIGHT_POS: Vec3 = Vec3::new(-2.0, 2.0, 4.0);
+
+/// Where the states of the bodies being displayed come from
+enum Source {
+    /// Computed step by step as the simulation runs
+    Live(OwningRun<3>),
+    /// Played back from a recorded trajectory
+    Replay(Replay),
+}
+
+/// Future positions of each body, from a copy of the live run kept running
+/// ahead of it
+struct Prediction {
+    run: OwningRun<3>,
+    paths: HashMap<String, VecDeque<Vector3>>,
+}
+
+impl Prediction {
+    /// Runs the copy on by `steps`, adding to the end of each path
+    fn extend(&mut self, steps: usize) {
+        for step in self.run.by_ref().take(steps) {
+            for (label, body) in step.body_map.iter() {
+                self.paths
+                    .entry(label.clone())
+                    .or_default()
+                    .push_back(body.position);
+            }
+        }
+    }
+}
+
 pub struct Stage {
     pipeline: Pipeline,
-    scale: f32,
-    run: OwningRun<3>,
+    /// Pipeline for models blended with what's behind them, such as rings
+    transparent_pipeline: Pipeline,
+    scale: SceneScale,
+    source: Source,
+    last_update: f64,
+    /// Smoothed frame rate, used to report the speed relative to real time
+    fps: f32,
+    /// Simulated time of the displayed state
+    t: f32,
+    paused: bool,
+    /// Steps to take on the next update while paused
+    pending_steps: usize,
+    steps_per_frame: usize,
+    text: TextRenderer,
+    hud: Hud,
+    lines: LineRenderer,
+    points: PointRenderer,
+    skybox: Option<Skybox>,
+    show_grid: bool,
+    vectors: VectorOverlay,
+    /// Springs and rods between bodies, drawn as lines joining them
+    connections: Vec<Connection>,
     body_state_map: BodyStateMap,
-    ry: f32,
-    rx: f32,
+    shadows: bool,
+    /// Recent positions of each body, oldest first
+    trails: HashMap<String, VecDeque<Vector3>>,
+    show_trails: bool,
+    /// Predicted paths of the bodies, if shown
+    prediction: Option<Prediction>,
+    prediction_steps: usize,
     models: HashMap<String, Model>,
+    camera: Camera,
     trackball: Trackball,
+    /// Last mouse position while looking around with the free-fly camera
+    look_from: Option<(f32, f32)>,
+    /// Where the left mouse button was pressed, to tell clicks from drags
+    press_position: Option<Vec2>,
+    /// Time and picked body of the last click, to detect double clicks
+    last_click: Option<(f64, Option<String>)>,
+}
+
+/// Most simulation steps taken per frame when speeding up a live run, to
+/// keep the viewer responsive
+const MAX_STEPS_PER_FRAME: usize = 4096;
+const HUD_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+const GRID_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 0.25];
+const SPRING_COLOR: [f32; 4] = [1.0, 0.8, 0.3, 0.8];
+const ROD_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 0.8];
+/// Distance between grid lines, in scene units
+const GRID_SPACING: f32 = 0.025;
+/// Number of grid lines on each side of the origin
+const GRID_LINES: i32 = 20;
+
+/// Formats a simulated time in seconds as days, hours, minutes and seconds
+fn format_time(t: f32) -> String {
+    let sign = if t < 0.0 { "-" } else { "" };
+    let total = t.abs() as u64;
+    let (days, hours) = (total / 86_400, total % 86_400 / 3_600);
+    let (minutes, seconds) = (total % 3_600 / 60, total % 60);
+    format!(
+        "{}{}d {:02}:{:02}:{:02}",
+        sign, days, hours, minutes, seconds
+    )
 }
 
+/// Maximum distance in pixels the mouse can move between pressing and
+/// releasing the button for it to count as a click
+const CLICK_DISTANCE: f32 = 4.0;
+/// Maximum time in seconds between the clicks of a double click
+const DOUBLE_CLICK_TIME: f64 = 0.4;
+
 // Converts screen coordinates to normalized device coordinates [-1, 1]
 fn normalize(x: f32, y: f32, width: f32, height: f32) -> (f32, f32) {
     let s: f32 = min(width as i32, height as i32) as f32 - 1.0;
@@ -69,6 +262,11 @@ impl EventHandler for Stage {
         y: f32,
     ) {
         if let MouseButton::Left = button {
+            self.press_position = Some(vec2(x, y));
+            if !self.camera.uses_trackball() {
+                self.look_from = Some((x, y));
+                return;
+            }
             let (width, height) = ctx.screen_size();
             let (nx, ny) = normalize(x, y, width, height);
             self.trackball.start(nx, ny);
@@ -76,7 +274,10 @@ impl EventHandler for Stage {
     }
 
     fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
-        if self.trackball.is_active() {
+        if let Some((last_x, last_y)) = self.look_from {
+            self.camera.look(x - last_x, y - last_y);
+            self.look_from = Some((x, y));
+        } else if self.trackball.is_active() {
             let (width, height) = ctx.screen_size();
             let (nx, ny) = normalize(x, y, width, height);
             self.trackball.set_xy(nx, ny);
@@ -85,64 +286,168 @@ impl EventHandler for Stage {
 
     fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
         if let MouseButton::Left = button {
+            self.look_from = None;
             if self.trackball.is_active() {
                 let (width, height) = ctx.screen_size();
                 let (nx, ny) = normalize(x, y, width, height);
                 self.trackball.end(nx, ny);
             }
+            let pressed = self.press_position.take();
+            if pressed.is_some_and(|p| p.distance(vec2(x, y)) <= CLICK_DISTANCE) {
+                self.click(ctx, vec2(x, y));
+            }
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        // Horizontal scrolling has no vertical component
+        if y == 0.0 {
+            return;
         }
+        self.camera.zoom(y.signum());
     }
 
     fn key_down_event(
         &mut self,
         _ctx: &mut Context,
         keycode: miniquad::KeyCode,
-        _keymods: miniquad::KeyMods,
+        keymods: KeyMods,
         _repeat: bool,
     ) {
+        if self.camera.key_down(keycode) {
+            return;
+        }
+        if let Source::Replay(replay) = &mut self.source {
+            if replay.handle_key(keycode) {
+                // Don't join the trails across a jump in time
+                self.trails.clear();
+                return;
+            }
+        }
         match keycode {
-            KeyCode::Up => self.rx += 0.1,
-            KeyCode::Down => self.rx -= 0.1,
-            KeyCode::Left => self.ry -= 0.1,
-            KeyCode::Right => self.ry += 0.1,
+            KeyCode::Space => self.paused = !self.paused,
+            KeyCode::Period if self.paused => self.pending_steps += 1,
+            KeyCode::Minus => match &mut self.source {
+                Source::Live(_) => self.steps_per_frame = (self.steps_per_frame / 2).max(1),
+                Source::Replay(replay) => replay.scale_rate(0.5),
+            },
+            KeyCode::Equal => match &mut self.source {
+                Source::Live(_) => {
+                    self.steps_per_frame = (self.steps_per_frame * 2).min(MAX_STEPS_PER_FRAME)
+                }
+                Source::Replay(replay) => replay.scale_rate(2.0),
+            },
+            KeyCode::R => match &mut self.source {
+                Source::Live(run) => run.reverse(),
+                Source::Replay(replay) => replay.reverse(),
+            },
+            KeyCode::C => {
+                self.camera.cycle_mode();
+                self.trackball = Trackball::default();
+            }
+            KeyCode::Tab => self.camera.cycle_body(keymods.shift),
+            KeyCode::T => self.show_trails = !self.show_trails,
+            KeyCode::O => self.shadows = !self.shadows,
+            KeyCode::G => self.show_grid = !self.show_grid,
+            KeyCode::Key1 => self.vectors.net_force = !self.vectors.net_force,
+            KeyCode::Key2 => self.vectors.forces = !self.vectors.forces,
+            KeyCode::Key3 => self.vectors.velocity = !self.vectors.velocity,
+            KeyCode::F => {
+                let positions: Vec<Vector3> = self.body_state_map.values().map(|b| b.pos).collect();
+                self.scale.fit(&positions);
+            }
+            KeyCode::M => self.scale.toggle_mapping(),
+            KeyCode::LeftBracket => self.scale.radius_exaggeration /= 2.0,
+            KeyCode::RightBracket => self.scale.radius_exaggeration *= 2.0,
+            KeyCode::H => self.hud.visible = !self.hud.visible,
+            KeyCode::B if keymods.shift => self.hud.cycle_reference(),
+            KeyCode::B => self.hud.cycle_selected(),
+            KeyCode::P => match self.prediction {
+                Some(_) => self.prediction = None,
+                None => self.start_prediction(),
+            },
             _ => {}
         }
     }
 
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
+        self.camera.key_up(keycode);
+    }
+
     fn update(&mut self, _ctx: &mut Context) {
-        let step = self.run.next().unwrap();
+        let now = miniquad::date::now();
+        let dt = (now - self.last_update) as f32;
+        self.last_update = now;
+        if dt > 0.0 {
+            self.fps = 0.95 * self.fps + 0.05 / dt;
+        }
+        self.camera.update(dt);
 
-        for (label, body) in step.body_map.iter() {
-            self.body_state_map.get_mut(label).unwrap().pos = body.position;
-            self.body_state_map.get_mut(label).unwrap().rot = body.spin.angle;
-            self.body_state_map.get_mut(label).unwrap().diameter = body.diameter;
-            self.body_state_map.get_mut(label).unwrap().tilt = body.spin.tilt;
+        let steps = if self.paused {
+            std::mem::take(&mut self.pending_steps)
+        } else {
+            self.steps_per_frame
+        };
+        if steps == 0 {
+            return;
+        }
+
+        match &mut self.source {
+            Source::Live(run) => {
+                let step = match run.nth(steps - 1) {
+                    Some(step) => step,
+                    None => {
+                        self.paused = true;
+                        return;
+                    }
+                };
+                self.t = step.t;
+
+                for (label, body) in step.body_map.iter() {
+                    self.body_state_map.get_mut(label).unwrap().pos = body.position;
+                    self.body_state_map.get_mut(label).unwrap().velocity = body.velocity;
+                    self.body_state_map.get_mut(label).unwrap().mass = body.mass;
+                    self.body_state_map.get_mut(label).unwrap().orientation =
+                        body.attitude.orientation;
+                    self.body_state_map.get_mut(label).unwrap().diameter = body.diameter;
+                    self.body_state_map.get_mut(label).unwrap().forces = body.forces.clone();
+                }
+                self.advance_prediction(steps);
+            }
+            Source::Replay(replay) => {
+                if self.paused {
+                    replay.step_frames(steps);
+                } else {
+                    replay.advance(dt);
+                }
+                replay.update_body_states(&mut self.body_state_map);
+                self.t = replay.t();
+            }
         }
+        self.record_trails();
     }
 
     fn draw(&mut self, ctx: &mut Context) {
-        // model-view-projection matrix
         let (width, height) = ctx.screen_size();
-
-        let light_color = vec3(1.0, 1.0, 1.0);
-        let light_pos = vec3(-2.0, 2.0, 4.0);
-
-        let view_rot = self.trackball.view_rotation();
-
-        let view = Mat4::look_at_rh(
-            vec3(0.0, 0.0, 0.3),
-            vec3(0.0, 0.0, 0.0),
-            vec3(0.0, 1.0, 0.0),
-        ) * Mat4::from_quat(view_rot);
-
-        let projection =
-            Mat4::perspective_rh_gl(60.0f32.to_radians(), width / height, 0.01, 1_000_000.0);
+        let (view, projection) = self.view_projection(width / height);
 
         let mut uniforms = Uniforms::default();
         uniforms.view = view;
         uniforms.projection = projection;
-        uniforms.light_color = light_color;
-        uniforms.light_pos = light_pos;
+        uniforms.light_color = LIGHT_COLOR;
+        let lighting = SceneLighting::new(
+            &self.body_state_map,
+            &self.scale,
+            |label| {
+                self.models
+                    .values()
+                    .any(|m| m.emissive && m.bodies.iter().any(|b| b == label))
+            },
+            self.shadows,
+        );
+        (uniforms.lights, uniforms.occluders) = lighting.uniform_arrays();
+        uniforms.num_lights = lighting.lights.len() as i32;
+        uniforms.num_occluders = lighting.occluders.len() as i32;
 
         ctx.begin_default_pass(PassAction::Clear {
             color: Some((0., 0., 0., 0.)),
@@ -150,12 +455,46 @@ impl EventHandler for Stage {
             stencil: None,
         });
 
+        if let Some(skybox) = &self.skybox {
+            skybox.draw(ctx, view, projection);
+        }
+        // Lines don't write depth, so drawing the grid before the bodies
+        // leaves it beneath them wherever they overlap, as a backdrop
+        if self.show_grid {
+            self.queue_grid();
+            self.lines.draw(ctx, projection * view);
+        }
+
         ctx.apply_pipeline(&self.pipeline);
 
-        for (_, m) in &self.models {
-            m.draw_bodies(ctx, &self.body_state_map, &mut uniforms, self.scale);
+        for m in self.models.values().filter(|m| !m.is_transparent()) {
+            m.draw_bodies(ctx, &self.body_state_map, &uniforms, &self.scale);
         }
 
+        self.queue_points(ctx.dpi_scale());
+        self.points
+            .draw(ctx, projection * view, vec2(width, height));
+
+        ctx.apply_pipeline(&self.transparent_pipeline);
+        for m in self.models.values().filter(|m| m.is_transparent()) {
+            m.draw_bodies(ctx, &self.body_state_map, &uniforms, &self.scale);
+        }
+
+        self.queue_paths();
+        self.queue_connections();
+        self.vectors.queue(
+            &mut self.lines,
+            &mut self.text,
+            &self.body_state_map,
+            &self.scale,
+            projection * view,
+            vec2(width, height),
+        );
+        self.lines.draw(ctx, projection * view);
+
+        self.queue_hud(projection * view, vec2(width, height));
+        self.text.draw(ctx);
+
         ctx.end_render_pass();
         ctx.commit_frame();
     }
@@ -165,13 +504,12 @@ const VERTEX_SHADER: &str = include_str!("shaders/geo.vert");
 const FRAGMENT_SHADER: &str = include_str!("shaders/geo.frag");
 
 impl Stage {
-    const MAX_BODIES: usize = 256;
-
     pub fn new(
         context: &mut Context,
         simulation: Simulation<3>,
         mut models: HashMap<String, Model>,
         config_root: PathBuf,
+        viewer: ViewerConfig,
     ) -> Self {
         for (_, m) in &mut models {
             m.load(context, &config_root);
@@ -181,12 +519,15 @@ impl Stage {
             images: vec!["textureSource".to_string()],
             uniforms: UniformBlockLayout {
                 uniforms: vec![
-                    UniformDesc::new("model", UniformType::Mat4),
                     UniformDesc::new("view", UniformType::Mat4),
                     UniformDesc::new("projection", UniformType::Mat4),
-                    UniformDesc::new("normal_mat", UniformType::Mat4),
                     UniformDesc::new("light_color", UniformType::Float3),
-                    UniformDesc::new("light_pos", UniformType::Float3),
+                    UniformDesc::new("emissive", UniformType::Float1),
+                    UniformDesc::new("double_sided", UniformType::Float1),
+                    UniformDesc::new("num_lights", UniformType::Int1),
+                    UniformDesc::new("num_occluders", UniformType::Int1),
+                    UniformDesc::new("lights", UniformType::Float4).array(MAX_LIGHTS),
+                    UniformDesc::new("occluders", UniformType::Float4).array(MAX_OCCLUDERS),
                 ],
             },
         };
@@ -194,51 +535,381 @@ impl Stage {
         let mut pipeline_params = PipelineParams::default();
         pipeline_params.depth_test = Comparison::LessOrEqual;
         pipeline_params.depth_write = true;
+        // Vertices of the model's geometry, and the transform of each body
+        // drawn with it
+        let buffer_layouts = [
+            BufferLayout::default(),
+            BufferLayout {
+                step_func: VertexStep::PerInstance,
+                ..Default::default()
+            },
+        ];
+        let attributes = [
+            VertexAttribute::with_buffer("pos", VertexFormat::Float3, 0),
+            VertexAttribute::with_buffer("normal", VertexFormat::Float3, 0),
+            VertexAttribute::with_buffer("tex_coord", VertexFormat::Float2, 0),
+            VertexAttribute::with_buffer("inst_model", VertexFormat::Mat4, 1),
+            VertexAttribute::with_buffer("inst_normal_mat", VertexFormat::Mat4, 1),
+        ];
+        let transparent_pipeline = Pipeline::with_params(
+            context,
+            &buffer_layouts,
+            &attributes,
+            shader,
+            PipelineParams {
+                cull_face: CullFace::Nothing,
+                depth_test: Comparison::LessOrEqual,
+                depth_write: true,
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+        );
         let pipeline = Pipeline::with_params(
             context,
-            &[
-                BufferLayout::default(),
-                BufferLayout {
-                    step_func: VertexStep::PerInstance,
-                    ..Default::default()
-                },
-                BufferLayout {
-                    step_func: VertexStep::PerInstance,
-                    ..Default::default()
-                },
-            ],
-            &[
-                VertexAttribute::with_buffer("pos", VertexFormat::Float3, 0),
-                VertexAttribute::with_buffer("normal", VertexFormat::Float3, 0),
-                VertexAttribute::with_buffer("tex_coord", VertexFormat::Float2, 0),
-            ],
+            &buffer_layouts,
+            &attributes,
             shader,
             pipeline_params,
         );
 
         context.set_cull_face(CullFace::Back);
 
-        let mut inst_pos = Vec::with_capacity(Self::MAX_BODIES);
-        inst_pos.resize(simulation.bodies().len(), Vector3::default());
-        let mut inst_rot = Vec::with_capacity(Self::MAX_BODIES);
-        inst_rot.resize(simulation.bodies().len(), Vector3::default());
-
         let mut body_state_map = BodyStateMap::new();
         for b in simulation.bodies() {
             body_state_map.insert(b.label.clone(), b.into());
         }
 
+        let labels: Vec<String> = simulation
+            .bodies()
+            .iter()
+            .map(|b| b.label.clone())
+            .collect();
+        let camera = Camera::new(&viewer.camera, labels.clone());
+        let positions: Vec<Vector3> = simulation.bodies().iter().map(|b| b.position).collect();
+        let scale = SceneScale::new(&viewer.scale, &positions);
+        let epoch = viewer.epoch.as_deref().map(|epoch| {
+            hud::parse_date(epoch).expect("viewer epoch must be formatted as YYYY-MM-DDTHH:MM:SS")
+        });
+
+        let t = simulation.t_start();
+        let connections = simulation.connections().to_vec();
         let run = OwningRun::from(simulation);
 
-        Self {
+        let mut stage = Self {
             pipeline,
+            transparent_pipeline,
             body_state_map,
-            scale: 100_000_000.0,
-            run,
-            ry: 0.0,
-            rx: 0.0,
+            scale,
+            source: Source::Live(run),
+            last_update: miniquad::date::now(),
+            fps: 60.0,
+            t,
+            paused: false,
+            pending_steps: 0,
+            steps_per_frame: 1,
+            text: TextRenderer::new(context),
+            hud: Hud::new(labels, epoch, viewer.units),
+            lines: LineRenderer::new(context),
+            points: PointRenderer::new(context),
+            skybox: viewer
+                .skybox
+                .as_ref()
+                .and_then(|path| Skybox::load(context, &config_root.join(path))),
+            show_grid: viewer.grid,
+            vectors: VectorOverlay::default(),
+            connections,
+            shadows: viewer.shadows,
+            trails: HashMap::new(),
+            show_trails: viewer.trails,
+            prediction: None,
+            prediction_steps: viewer.prediction_steps,
             models: models,
+            camera,
             trackball: Trackball::default(),
+            look_from: None,
+            press_position: None,
+            last_click: None,
+        };
+        if viewer.prediction {
+            stage.start_prediction();
+        }
+        stage
+    }
+
+    /// Plays back a recorded trajectory instead of running the simulation
+    pub fn with_replay(mut self, trajectory: Trajectory) -> Self {
+        if let Source::Live(run) = &self.source {
+            let replay = Replay::new(trajectory, run.simulation());
+            replay.update_body_states(&mut self.body_state_map);
+            self.t = replay.t();
+            self.source = Source::Replay(replay);
+            self.prediction = None;
+        }
+        self
+    }
+
+    /// Returns the view and projection matrices for the current camera
+    fn view_projection(&self, aspect_ratio: f32) -> (Mat4, Mat4) {
+        let view_rot = self.trackball.view_rotation();
+        let view = self
+            .camera
+            .view(view_rot, &self.body_state_map, &self.scale);
+        let projection = Mat4::perspective_rh_gl(
+            self.camera.fov().to_radians(),
+            aspect_ratio,
+            0.01,
+            1_000_000.0,
+        );
+        (view, projection)
+    }
+
+    /// Selects the body under the cursor, or follows it with the camera if
+    /// it was double clicked.
+    fn click(&mut self, ctx: &mut Context, point: Vec2) {
+        let (width, height) = ctx.screen_size();
+        let (view, projection) = self.view_projection(width / height);
+        let picked = picking::pick(
+            point,
+            vec2(width, height),
+            projection * view,
+            &self.body_state_map,
+            &self.scale,
+            ctx.dpi_scale(),
+        );
+
+        let now = miniquad::date::now();
+        let double_click = matches!(
+            &self.last_click,
+            Some((time, last)) if now - time <= DOUBLE_CLICK_TIME && *last == picked
+        );
+        match (&picked, double_click) {
+            (Some(label), true) => {
+                self.camera.follow(label);
+                self.trackball = Trackball::default();
+                self.last_click = None;
+            }
+            _ => self.last_click = Some((now, picked.clone())),
+        }
+        self.hud.select(picked.as_deref());
+    }
+
+    /// Adds the current position of each body to its trail
+    fn record_trails(&mut self) {
+        for model in self.models.values() {
+            for label in &model.bodies {
+                let body_state = match self.body_state_map.get(label) {
+                    Some(body_state) => body_state,
+                    None => continue,
+                };
+                let trail = self.trails.entry(label.clone()).or_default();
+                trail.push_back(body_state.pos);
+                while trail.len() > model.trail_length {
+                    trail.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Runs a copy of the live simulation ahead to predict the path of
+    /// each body. Recorded trajectories aren't predicted.
+    fn start_prediction(&mut self) {
+        let run = match &self.source {
+            Source::Live(run) => run.clone(),
+            Source::Replay(_) => return,
+        };
+        let mut prediction = Prediction {
+            run,
+            paths: HashMap::new(),
+        };
+        prediction.extend(self.prediction_steps);
+        self.prediction = Some(prediction);
+    }
+
+    /// Moves the prediction on by the `steps` the live run has taken,
+    /// running its copy only as far as needed to keep the same look-ahead
+    fn advance_prediction(&mut self, steps: usize) {
+        let (prediction, run) = match (&mut self.prediction, &self.source) {
+            (Some(prediction), Source::Live(run)) => (prediction, run),
+            _ => return,
+        };
+        // After reversing, or moving past the end of the look-ahead, the
+        // prediction starts afresh
+        if prediction.run.is_reversed() != run.is_reversed() || steps >= self.prediction_steps {
+            self.start_prediction();
+            return;
+        }
+        for path in prediction.paths.values_mut() {
+            path.drain(..steps.min(path.len()));
+        }
+        prediction.extend(steps);
+    }
+
+    /// Queues a square grid centred on the origin of the scene in the xz
+    /// plane, the plane the bodies' equators are in without tilt, and the
+    /// x, y and z axes in red, green and blue.
+    fn queue_grid(&mut self) {
+        let extent = GRID_SPACING * GRID_LINES as f32;
+        for i in -GRID_LINES..=GRID_LINES {
+            let offset = GRID_SPACING * i as f32;
+            self.lines.queue_segment(
+                vec3(offset, 0.0, -extent),
+                vec3(offset, 0.0, extent),
+                GRID_COLOR,
+                GRID_COLOR,
+            );
+            self.lines.queue_segment(
+                vec3(-extent, 0.0, offset),
+                vec3(extent, 0.0, offset),
+                GRID_COLOR,
+                GRID_COLOR,
+            );
+        }
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            let color = axis.extend(0.8).to_array();
+            self.lines
+                .queue_segment(Vec3::ZERO, axis * extent, color, color);
+        }
+    }
+
+    /// Queues the bodies of point sprite models, in the colour of their
+    /// models.
+    fn queue_points(&mut self, dpi_scale: f32) {
+        for model in self.models.values() {
+            if model.shape != Shape::Point {
+                continue;
+            }
+            let color = model.overlay_color.extend(1.0).to_array();
+            let size = model.point_size() * dpi_scale;
+            for label in &model.bodies {
+                if let Some(body_state) = self.body_state_map.get(label) {
+                    let pos = self.scale.position(&body_state.pos);
+                    self.points.queue_point(pos, size, color);
+                }
+            }
+        }
+    }
+
+    /// Queues the trails and predicted paths of the bodies, in the colour
+    /// of their models.
+    fn queue_paths(&mut self) {
+        for model in self.models.values() {
+            let color = model.overlay_color.extend(0.8).to_array();
+            for label in &model.bodies {
+                if let (true, Some(trail)) = (self.show_trails, self.trails.get(label)) {
+                    let points: Vec<Vec3> = trail.iter().map(|p| self.scale.position(p)).collect();
+                    self.lines.queue_strip(&points, color, true);
+                }
+                if let Some(path) = self.prediction.as_ref().and_then(|p| p.paths.get(label)) {
+                    let points: Vec<Vec3> = path.iter().map(|p| self.scale.position(p)).collect();
+                    self.lines.queue_dashed(&points, color);
+                }
+            }
+        }
+    }
+
+    /// Queues a line between the bodies at the ends of each spring and rod
+    fn queue_connections(&mut self) {
+        for connection in &self.connections {
+            let [a, b] = connection.bodies();
+            let (a, b) = match (self.body_state_map.get(a), self.body_state_map.get(b)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            let color = match connection {
+                Connection::Spring { .. } => SPRING_COLOR,
+                Connection::Rod { .. } => ROD_COLOR,
+            };
+            self.lines.queue_segment(
+                self.scale.position(&a.pos),
+                self.scale.position(&b.pos),
+                color,
+                color,
+            );
+        }
+    }
+
+    /// Queues the status lines at the top left of the screen, and if the
+    /// HUD is shown, the body labels and the selected body's details.
+    fn queue_hud(&mut self, view_projection: Mat4, screen_size: Vec2) {
+        let lh = self.text.line_height();
+        let mut lines: Vec<String> = self.hud.date(self.t).into_iter().collect();
+        lines.push(self.status());
+        lines.push(self.camera_status());
+        for (i, line) in lines.iter().enumerate() {
+            self.text.queue(line, lh, (i + 1) as f32 * lh, HUD_COLOR);
+        }
+        if !self.hud.visible {
+            return;
+        }
+
+        self.hud.queue_labels(
+            &mut self.text,
+            &self.body_state_map,
+            &self.models,
+            view_projection,
+            screen_size,
+            &self.scale,
+        );
+        let panel = self.hud.panel(&self.body_state_map);
+        let top = screen_size.y - (panel.len() + 1) as f32 * lh;
+        for (i, line) in panel.iter().enumerate() {
+            self.text.queue(line, lh, top + i as f32 * lh, HUD_COLOR);
+        }
+    }
+
+    /// Describes the camera mode and the bodies it's tracking, and how the
+    /// scene is scaled
+    fn camera_status(&self) -> String {
+        let target = self.camera.target().unwrap_or_default();
+        let camera = match self.camera.mode {
+            CameraMode::Origin => String::from("camera: origin"),
+            CameraMode::Follow => format!("camera: following {}", target),
+            CameraMode::LookAt => format!(
+                "camera: {} from {}",
+                target,
+                self.camera.from().unwrap_or_default()
+            ),
+            CameraMode::FreeFly => String::from("camera: free-fly"),
+        };
+        let mapping = match self.scale.mapping {
+            DistanceMapping::Linear => "linear",
+            DistanceMapping::Logarithmic => "logarithmic",
+        };
+        format!(
+            "{}  {} distances  radii x{}",
+            camera, mapping, self.scale.radius_exaggeration
+        )
+    }
+
+    /// Describes the simulated time and the speed it's progressing at
+    fn status(&self) -> String {
+        let (rate, speed, reversed) = match &self.source {
+            Source::Live(run) => (
+                format!(
+                    "{} steps/frame, {:.0} steps/s",
+                    self.steps_per_frame,
+                    self.steps_per_frame as f32 * self.fps
+                ),
+                self.steps_per_frame as f32 * run.t_step().abs() * self.fps,
+                run.is_reversed(),
+            ),
+            Source::Replay(replay) => (
+                String::from("replay"),
+                replay.rate().abs(),
+                replay.rate() < 0.0,
+            ),
+        };
+        let mut status = format!("t = {}  {}  ({:.0}x)", format_time(self.t), rate, speed);
+        if reversed {
+            status.push_str("  reversed");
+        }
+        if self.paused {
+            status.push_str("  paused");
         }
+        status
     }
 }
@@ -0,0 +1,267 @@
+use glam::{vec3, Mat4, Quat, Vec3};
+use miniquad::KeyCode;
+use serde::{Deserialize, Serialize};
+
+use super::scale::SceneScale;
+use super::{to_vec3, BodyStateMap};
+use crate::math::Vector3;
+
+const MIN_DISTANCE: f32 = 0.02;
+const MIN_FOV: f32 = 1.0;
+const MAX_FOV: f32 = 120.0;
+// Radians the free-fly camera turns per pixel of mouse movement
+const LOOK_SENSITIVITY: f32 = 0.005;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraMode {
+    /// Orbits the origin of the scene
+    #[default]
+    Origin,
+    /// Orbits the target body, keeping it centred as it moves
+    Follow,
+    /// Looks from the surface of one body towards the target body
+    LookAt,
+    /// Moves freely with the keyboard and looks around with the mouse
+    FreeFly,
+}
+
+impl CameraMode {
+    fn next(self) -> Self {
+        match self {
+            CameraMode::Origin => CameraMode::Follow,
+            CameraMode::Follow => CameraMode::LookAt,
+            CameraMode::LookAt => CameraMode::FreeFly,
+            CameraMode::FreeFly => CameraMode::Origin,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub mode: CameraMode,
+    /// Label of the body to follow or look at, defaulting to the first body
+    pub target: Option<String>,
+    /// Label of the body to look from, defaulting to the second body
+    pub from: Option<String>,
+    /// Distance from the point being orbited, in scene units
+    pub distance: f32,
+    /// Starting position when flying freely, in scene units
+    pub position: Vector3,
+    /// Free-fly movement speed, in scene units per second
+    pub fly_speed: f32,
+    /// Vertical field of view, in degrees
+    pub fov: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::Origin,
+            target: None,
+            from: None,
+            distance: 0.3,
+            position: Vector3::new(0.0, 0.0, 0.3),
+            fly_speed: 0.1,
+            fov: 60.0,
+        }
+    }
+}
+
+pub struct Camera {
+    pub mode: CameraMode,
+    labels: Vec<String>,
+    target: usize,
+    from: usize,
+    distance: f32,
+    position: Vec3,
+    yaw: f32,
+    pitch: f32,
+    fly_speed: f32,
+    fov: f32,
+    /// Free-fly movement keys currently held
+    held: Vec<KeyCode>,
+}
+
+impl Camera {
+    /// Creates a camera that can target any of the bodies with the given
+    /// labels, in the order they are cycled through.
+    pub fn new(config: &CameraConfig, labels: Vec<String>) -> Self {
+        let index_of = |label: &Option<String>, default: usize| {
+            label
+                .as_ref()
+                .and_then(|l| labels.iter().position(|b| b == l))
+                .unwrap_or(default.min(labels.len().saturating_sub(1)))
+        };
+        let target = index_of(&config.target, 0);
+        let from = index_of(&config.from, 1);
+        Self {
+            mode: config.mode,
+            target,
+            from,
+            labels,
+            distance: config.distance,
+            position: to_vec3(&config.position),
+            yaw: 0.0,
+            pitch: 0.0,
+            fly_speed: config.fly_speed,
+            fov: config.fov,
+            held: Vec::new(),
+        }
+    }
+
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.labels.get(self.target).map(String::as_str)
+    }
+
+    pub fn from(&self) -> Option<&str> {
+        self.labels.get(self.from).map(String::as_str)
+    }
+
+    /// Whether dragging with the mouse should rotate the view with the
+    /// trackball, rather than being handled by the camera.
+    pub fn uses_trackball(&self) -> bool {
+        matches!(self.mode, CameraMode::Origin | CameraMode::Follow)
+    }
+
+    pub fn cycle_mode(&mut self) {
+        self.mode = self.mode.next();
+    }
+
+    /// Switches to following the body with the given label
+    pub fn follow(&mut self, label: &str) {
+        if let Some(i) = self.labels.iter().position(|l| l == label) {
+            self.target = i;
+            self.mode = CameraMode::Follow;
+        }
+    }
+
+    /// Selects the next body as the target, or as the body to look from
+    pub fn cycle_body(&mut self, from: bool) {
+        let n = self.labels.len().max(1);
+        if from {
+            self.from = (self.from + 1) % n;
+        } else {
+            self.target = (self.target + 1) % n;
+        }
+    }
+
+    /// Zooms by the given number of mouse wheel steps, positive being in
+    pub fn zoom(&mut self, steps: f32) {
+        let factor = 0.9f32.powf(steps);
+        match self.mode {
+            CameraMode::Origin | CameraMode::Follow => {
+                self.distance = (self.distance * factor).max(MIN_DISTANCE)
+            }
+            CameraMode::LookAt | CameraMode::FreeFly => {
+                self.fov = (self.fov * factor).clamp(MIN_FOV, MAX_FOV)
+            }
+        }
+    }
+
+    /// Turns the free-fly camera by a mouse movement in pixels
+    pub fn look(&mut self, dx: f32, dy: f32) {
+        self.yaw -= dx * LOOK_SENSITIVITY;
+        self.pitch = (self.pitch - dy * LOOK_SENSITIVITY).clamp(-1.55, 1.55);
+    }
+
+    fn orientation(&self) -> Quat {
+        Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(self.pitch)
+    }
+
+    /// Handles movement keys, returning whether the key was used.
+    pub fn key_down(&mut self, keycode: KeyCode) -> bool {
+        if self.mode != CameraMode::FreeFly || Self::move_direction(keycode).is_none() {
+            return false;
+        }
+        if !self.held.contains(&keycode) {
+            self.held.push(keycode);
+        }
+        true
+    }
+
+    pub fn key_up(&mut self, keycode: KeyCode) {
+        self.held.retain(|k| *k != keycode);
+    }
+
+    fn move_direction(keycode: KeyCode) -> Option<Vec3> {
+        match keycode {
+            KeyCode::W => Some(Vec3::NEG_Z),
+            KeyCode::S => Some(Vec3::Z),
+            KeyCode::A => Some(Vec3::NEG_X),
+            KeyCode::D => Some(Vec3::X),
+            KeyCode::E => Some(Vec3::Y),
+            KeyCode::Q => Some(Vec3::NEG_Y),
+            _ => None,
+        }
+    }
+
+    /// Moves the free-fly camera for the keys held over `dt` seconds
+    pub fn update(&mut self, dt: f32) {
+        if self.mode != CameraMode::FreeFly {
+            return;
+        }
+        let direction: Vec3 = self
+            .held
+            .iter()
+            .filter_map(|k| Self::move_direction(*k))
+            .sum();
+        self.position += self.orientation() * direction * self.fly_speed * dt;
+    }
+
+    fn body_position(
+        &self,
+        index: usize,
+        body_state_map: &BodyStateMap,
+        scale: &SceneScale,
+    ) -> Vec3 {
+        self.labels
+            .get(index)
+            .and_then(|l| body_state_map.get(l))
+            .map(|b| scale.position(&b.pos))
+            .unwrap_or(Vec3::ZERO)
+    }
+
+    /// Returns the view matrix for the current mode, with `rotation` being
+    /// the trackball rotation applied when orbiting.
+    pub fn view(&self, rotation: Quat, body_state_map: &BodyStateMap, scale: &SceneScale) -> Mat4 {
+        match self.mode {
+            CameraMode::Origin | CameraMode::Follow => {
+                let center = match self.mode {
+                    CameraMode::Follow => self.body_position(self.target, body_state_map, scale),
+                    _ => Vec3::ZERO,
+                };
+                Mat4::look_at_rh(vec3(0.0, 0.0, self.distance), Vec3::ZERO, Vec3::Y)
+                    * Mat4::from_quat(rotation)
+                    * Mat4::from_translation(-center)
+            }
+            CameraMode::LookAt => {
+                let target = self.body_position(self.target, body_state_map, scale);
+                let from = self.body_position(self.from, body_state_map, scale);
+                // Start just outside the surface of the body being looked from
+                let radius = self
+                    .labels
+                    .get(self.from)
+                    .and_then(|l| body_state_map.get(l))
+                    .map(|b| scale.size(b.diameter))
+                    .unwrap_or_default();
+                let direction = (target - from).try_normalize().unwrap_or(Vec3::NEG_Z);
+                let eye = from + direction * radius * 1.1;
+                let up = match direction.cross(Vec3::Y).length() {
+                    l if l < 1e-3 => Vec3::Z,
+                    _ => Vec3::Y,
+                };
+                Mat4::look_at_rh(eye, target, up)
+            }
+            CameraMode::FreeFly => {
+                let forward = self.orientation() * Vec3::NEG_Z;
+                Mat4::look_at_rh(self.position, self.position + forward, Vec3::Y)
+            }
+        }
+    }
+}
@@ -0,0 +1,490 @@
+// Software rasterizer that renders the same scene as `Stage::draw` to image
+// files, for batch jobs without a window or GPU.
+use glam::{vec2, vec3, Mat3, Mat4, Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, Rgb, RgbImage, Rgba, RgbaImage};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+
+use super::lighting::{visibility, SceneLighting};
+use super::model::{average_color, Model, Shape, Vertex};
+use super::scale::SceneScale;
+use super::{to_vec3, BodyStateMap, LIGHT_COLOR};
+use crate::math::{Vector2, Vector3};
+use crate::simulation::{OwningRun, Simulation};
+
+// Ambient lighting strength, matching shaders/geo.frag
+const AMBIENT_STRENGTH: f32 = 0.1;
+const NEAR: f32 = 0.01;
+const FAR: f32 = 1_000_000.0;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderCamera {
+    pub eye: Vector3,
+    pub target: Vector3,
+    pub up: Vector3,
+    /// Vertical field of view, in degrees
+    pub fov: f32,
+}
+
+impl Default for RenderCamera {
+    fn default() -> Self {
+        Self {
+            eye: Vector3::new(0.0, 0.0, 0.3),
+            target: Vector3::new(0.0, 0.0, 0.0),
+            up: Vector3::new(0.0, 1.0, 0.0),
+            fov: 60.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderConfig {
+    #[serde(deserialize_with = "deserialize_size")]
+    pub width: u32,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub height: u32,
+    /// Number of frames to render, fewer if the simulation ends first
+    pub frames: usize,
+    /// Number of simulation steps between consecutive frames
+    pub steps_per_frame: usize,
+    /// Simulation distance corresponding to one unit in the scene
+    pub scale: f32,
+    pub camera: RenderCamera,
+    /// Whether bodies cast shadows on each other
+    pub shadows: bool,
+    /// Whether to also encode the frames as an animated GIF
+    pub gif: bool,
+    /// Time each frame is shown for in the GIF, in milliseconds
+    pub gif_frame_delay: u32,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            frames: 100,
+            steps_per_frame: 1,
+            scale: 100_000_000.0,
+            camera: RenderCamera::default(),
+            shadows: true,
+            gif: false,
+            gif_frame_delay: 40,
+        }
+    }
+}
+
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let size = u32::deserialize(deserializer)?;
+    if size == 0 {
+        return Err(serde::de::Error::custom(
+            "render width and height must be at least 1 pixel",
+        ));
+    }
+    Ok(size)
+}
+
+/// Attributes passed from the vertex to the fragment stage
+#[derive(Clone, Copy)]
+struct Varying {
+    clip: Vec4,
+    frag_pos: Vec3,
+    normal: Vec3,
+    tex_coord: Vec2,
+}
+
+struct Target {
+    color: RgbImage,
+    depth: Vec<f32>,
+}
+
+/// How a model's surface is shaded
+struct Material<'a> {
+    texture: &'a RgbaImage,
+    emissive: bool,
+    double_sided: bool,
+    lighting: &'a SceneLighting,
+}
+
+struct HeadlessModel {
+    texture: RgbaImage,
+    bodies: Vec<String>,
+    emissive: bool,
+    double_sided: bool,
+    /// Diameter in pixels and colour of point sprites, drawn instead of the
+    /// geometry
+    point: Option<(f32, Vec3)>,
+    shape_matrix: Mat4,
+    vertices: Vec<Vertex<Vector3, Vector2>>,
+    indices: Vec<u32>,
+}
+
+pub struct HeadlessRenderer {
+    config: RenderConfig,
+    run: OwningRun<3>,
+    /// Models in drawing order, with transparent models last
+    models: Vec<HeadlessModel>,
+}
+
+impl HeadlessRenderer {
+    pub fn new(
+        simulation: Simulation<3>,
+        models: &HashMap<String, Model>,
+        config_root: &Path,
+        config: RenderConfig,
+    ) -> Self {
+        let mut models: Vec<&Model> = models.values().collect();
+        models.sort_by_key(|m| m.is_transparent());
+        let models = models
+            .into_iter()
+            .map(|m| {
+                let texture = m.load_image(config_root);
+                let (vertices, indices) = m.geometry(config_root);
+                let point =
+                    (m.shape == Shape::Point).then(|| (m.point_size(), average_color(&texture)));
+                HeadlessModel {
+                    texture,
+                    bodies: m.bodies.clone(),
+                    emissive: m.emissive,
+                    double_sided: m.shape == Shape::Ring,
+                    point,
+                    shape_matrix: m.shape_matrix(),
+                    vertices,
+                    indices,
+                }
+            })
+            .collect();
+        Self {
+            config,
+            run: OwningRun::from(simulation),
+            models,
+        }
+    }
+
+    /// Renders frames from the simulation run to a numbered PNG sequence in
+    /// `output_dir`, and to an animated GIF if configured.
+    pub fn render(&mut self, output_dir: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+        fs::create_dir_all(output_dir)?;
+        let mut gif = if self.config.gif {
+            let file = File::create(output_dir.join("frames.gif"))?;
+            let mut encoder = GifEncoder::new_with_speed(file, 10);
+            encoder.set_repeat(Repeat::Infinite)?;
+            Some(encoder)
+        } else {
+            None
+        };
+
+        let mut body_state_map = BodyStateMap::new();
+        for frame in 0..self.config.frames {
+            let skip = match frame {
+                0 => 0,
+                _ => self.config.steps_per_frame.max(1) - 1,
+            };
+            let step = match self.run.nth(skip) {
+                Some(step) => step,
+                None => break,
+            };
+            for (label, body) in step.body_map.iter() {
+                body_state_map.insert(label.clone(), body.into());
+            }
+
+            let img = self.draw(&body_state_map);
+            img.save(output_dir.join(format!("frame_{:06}.png", frame)))?;
+            if let Some(encoder) = gif.as_mut() {
+                let delay = Delay::from_numer_denom_ms(self.config.gif_frame_delay, 1);
+                let rgba = image::DynamicImage::ImageRgb8(img).to_rgba8();
+                encoder.encode_frame(Frame::from_parts(rgba, 0, 0, delay))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&self, body_state_map: &BodyStateMap) -> RgbImage {
+        let (width, height) = (self.config.width, self.config.height);
+        let camera = &self.config.camera;
+        let view = Mat4::look_at_rh(
+            to_vec3(&camera.eye),
+            to_vec3(&camera.target),
+            to_vec3(&camera.up),
+        );
+        let projection = Mat4::perspective_rh_gl(
+            camera.fov.to_radians(),
+            width as f32 / height as f32,
+            NEAR,
+            FAR,
+        );
+
+        let scale = SceneScale::linear(self.config.scale);
+        let lighting = SceneLighting::new(
+            body_state_map,
+            &scale,
+            |label| {
+                self.models
+                    .iter()
+                    .any(|m| m.emissive && m.bodies.iter().any(|b| b == label))
+            },
+            self.config.shadows,
+        );
+        let mut target = Target {
+            color: RgbImage::new(width, height),
+            depth: vec![f32::INFINITY; (width * height) as usize],
+        };
+        for headless_model in &self.models {
+            let material = Material {
+                texture: &headless_model.texture,
+                emissive: headless_model.emissive,
+                double_sided: headless_model.double_sided,
+                lighting: &lighting,
+            };
+            for label in &headless_model.bodies {
+                let body_state = match body_state_map.get(label) {
+                    Some(body_state) => body_state,
+                    None => continue,
+                };
+                let view_projection = projection * view;
+                if let Some((size, color)) = headless_model.point {
+                    let clip = view_projection * scale.position(&body_state.pos).extend(1.0);
+                    draw_point(&mut target, clip, size, color);
+                    continue;
+                }
+                let model = body_state.model_matrix(&scale) * headless_model.shape_matrix;
+                let normal_mat = Mat3::from_mat4(model.inverse().transpose());
+
+                let varyings: Vec<Varying> = headless_model
+                    .vertices
+                    .iter()
+                    .map(|v| {
+                        let frag_pos = model.transform_point3(to_vec3(&v.pos));
+                        Varying {
+                            clip: view_projection * frag_pos.extend(1.0),
+                            frag_pos,
+                            normal: normal_mat * to_vec3(&v.normal),
+                            tex_coord: vec2(v.tex_coord.x(), v.tex_coord.y()),
+                        }
+                    })
+                    .collect();
+                for triangle in headless_model.indices.chunks_exact(3) {
+                    let tri = [
+                        varyings[triangle[0] as usize],
+                        varyings[triangle[1] as usize],
+                        varyings[triangle[2] as usize],
+                    ];
+                    rasterize(&mut target, &tri, &material);
+                }
+            }
+        }
+        target.color
+    }
+}
+
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn rasterize(target: &mut Target, tri: &[Varying; 3], material: &Material) {
+    // Triangles crossing the near plane are rare for distant bodies, so
+    // they're dropped rather than clipped.
+    if tri.iter().any(|v| v.clip.z < -v.clip.w || v.clip.w <= 0.0) {
+        return;
+    }
+    let (width, height) = target.color.dimensions();
+    let screen: Vec<Vec3> = tri
+        .iter()
+        .map(|v| {
+            let ndc = v.clip.xyz() / v.clip.w;
+            vec3(
+                (ndc.x + 1.0) / 2.0 * width as f32,
+                (1.0 - ndc.y) / 2.0 * height as f32,
+                ndc.z * 0.5 + 0.5,
+            )
+        })
+        .collect();
+    let (a, b, c) = (screen[0].xy(), screen[1].xy(), screen[2].xy());
+    let area = edge(a, b, c);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+
+    let min = a.min(b).min(c).max(Vec2::ZERO);
+    let max = a
+        .max(b)
+        .max(c)
+        .min(vec2(width as f32 - 1.0, height as f32 - 1.0));
+    for y in (min.y as u32)..=(max.y.max(0.0) as u32) {
+        for x in (min.x as u32)..=(max.x.max(0.0) as u32) {
+            let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let weights = vec3(edge(b, c, p), edge(c, a, p), edge(a, b, p)) / area;
+            if weights.min_element() < 0.0 {
+                continue;
+            }
+            let z = weights.dot(vec3(screen[0].z, screen[1].z, screen[2].z));
+            let index = (y * width + x) as usize;
+            if z > target.depth[index] {
+                continue;
+            }
+
+            // Perspective-correct interpolation of the vertex attributes
+            let w = weights / vec3(tri[0].clip.w, tri[1].clip.w, tri[2].clip.w);
+            let w = w / (w.x + w.y + w.z);
+            let frag = Varying {
+                clip: Vec4::ZERO,
+                frag_pos: w.x * tri[0].frag_pos + w.y * tri[1].frag_pos + w.z * tri[2].frag_pos,
+                normal: w.x * tri[0].normal + w.y * tri[1].normal + w.z * tri[2].normal,
+                tex_coord: w.x * tri[0].tex_coord + w.y * tri[1].tex_coord + w.z * tri[2].tex_coord,
+            };
+            let color = shade(&frag, material);
+            if color.w < 0.01 {
+                continue;
+            }
+            let behind = target.color.get_pixel(x, y).0.map(|c| c as f32 / 255.0);
+            let blended = vec3(behind[0], behind[1], behind[2]).lerp(color.xyz(), color.w);
+            target.depth[index] = z;
+            target.color.put_pixel(x, y, to_rgb(blended));
+        }
+    }
+}
+
+/// Draws a point sprite as a disc `size` pixels across, matching
+/// shaders/point.frag
+fn draw_point(target: &mut Target, clip: Vec4, size: f32, color: Vec3) {
+    if clip.z < -clip.w || clip.w <= 0.0 {
+        return;
+    }
+    let (width, height) = target.color.dimensions();
+    let ndc = clip.xyz() / clip.w;
+    let center = vec2(
+        (ndc.x + 1.0) / 2.0 * width as f32,
+        (1.0 - ndc.y) / 2.0 * height as f32,
+    );
+    let z = ndc.z * 0.5 + 0.5;
+    let radius = size / 2.0;
+    let min = (center - radius).max(Vec2::ZERO);
+    let max = (center + radius).min(vec2(width as f32 - 1.0, height as f32 - 1.0));
+    for y in (min.y as u32)..=(max.y.max(0.0) as u32) {
+        for x in (min.x as u32)..=(max.x.max(0.0) as u32) {
+            let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let index = (y * width + x) as usize;
+            if p.distance(center) > radius || z > target.depth[index] {
+                continue;
+            }
+            target.depth[index] = z;
+            target.color.put_pixel(x, y, to_rgb(color));
+        }
+    }
+}
+
+fn to_rgb(color: Vec3) -> Rgb<u8> {
+    let color = color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0;
+    Rgb([color.x as u8, color.y as u8, color.z as u8])
+}
+
+/// Lights a fragment the same way as shaders/geo.frag, returning its
+/// colour and opacity
+fn shade(frag: &Varying, material: &Material) -> Vec4 {
+    let tex_color = sample(material.texture, frag.tex_coord);
+    let (tex_color, alpha) = (tex_color.xyz(), tex_color.w);
+    let color = if material.emissive {
+        tex_color
+    } else {
+        let ambient = AMBIENT_STRENGTH * LIGHT_COLOR;
+        let norm = frag.normal.normalize();
+        let diffuse: Vec3 = material
+            .lighting
+            .lights
+            .iter()
+            .map(|light| {
+                let light_dir = (light.xyz() - frag.frag_pos).normalize();
+                let facing = if material.double_sided {
+                    norm.dot(light_dir).abs()
+                } else {
+                    norm.dot(light_dir)
+                };
+                let strength = if facing > 0.0 {
+                    facing * visibility(frag.frag_pos, *light, &material.lighting.occluders)
+                } else {
+                    0.0
+                };
+                strength * LIGHT_COLOR
+            })
+            .sum();
+        (ambient + diffuse) * tex_color
+    };
+    color.extend(alpha)
+}
+
+/// Bilinearly samples the texture with repeat wrapping
+fn sample(texture: &RgbaImage, uv: Vec2) -> Vec4 {
+    let (width, height) = texture.dimensions();
+    let x = uv.x * width as f32 - 0.5;
+    let y = uv.y * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let texel = |tx: f32, ty: f32| {
+        let tx = (tx as i64).rem_euclid(width as i64) as u32;
+        let ty = (ty as i64).rem_euclid(height as i64) as u32;
+        let Rgba([r, g, b, a]) = *texture.get_pixel(tx, ty);
+        Vec4::new(r as f32, g as f32, b as f32, a as f32) / 255.0
+    };
+    let top = texel(x0, y0).lerp(texel(x0 + 1.0, y0), fx);
+    let bottom = texel(x0, y0 + 1.0).lerp(texel(x0 + 1.0, y0 + 1.0), fx);
+    top.lerp(bottom, fy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Body;
+
+    #[test]
+    fn empty_renders_are_rejected() {
+        assert!(serde_yaml::from_str::<RenderConfig>("width: 0").is_err());
+        assert!(serde_yaml::from_str::<RenderConfig>("height: 0").is_err());
+        let config: RenderConfig = serde_yaml::from_str("width: 32").unwrap();
+        assert_eq!((config.width, config.height), (32, 600));
+    }
+
+    #[test]
+    fn bodies_in_front_of_the_camera_are_drawn_over_the_background() {
+        let mut simulation = Simulation::new(Some(0.0), Some(1.0), Some(1.0));
+        simulation.add_body(Body::new(
+            String::from("Sun"),
+            1.0,
+            1e8,
+            Vector3::default(),
+            Vector3::default(),
+            Default::default(),
+        ));
+        let models: HashMap<String, Model> = serde_yaml::from_str(
+            "sun: {shape: sphere, color: [1.0, 1.0, 1.0], bodies: [Sun], emissive: true}",
+        )
+        .unwrap();
+        let config = RenderConfig {
+            width: 32,
+            height: 32,
+            frames: 1,
+            camera: RenderCamera {
+                eye: Vector3::new(0.0, 0.0, 5.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let output_dir = std::env::temp_dir().join(format!("headless_{}", std::process::id()));
+        HeadlessRenderer::new(simulation, &models, Path::new("."), config)
+            .render(&output_dir)
+            .unwrap();
+
+        let frame = image::open(output_dir.join("frame_000000.png"))
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(frame.dimensions(), (32, 32));
+        assert_eq!(frame.get_pixel(16, 16).0, [255, 255, 255]);
+        assert_eq!(frame.get_pixel(0, 0).0, [0, 0, 0]);
+        fs::remove_dir_all(output_dir).unwrap();
+    }
+}
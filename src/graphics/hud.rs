@@ -0,0 +1,284 @@
+use glam::{vec2, Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::model::Model;
+use super::scale::SceneScale;
+use super::text::TextRenderer;
+use super::BodyStateMap;
+use crate::math::Distance;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Converts a calendar date to days since 1970-01-01
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since 1970-01-01 to a calendar (year, month, day)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Screen position in pixels from the top left of a point in the scene,
+/// or nothing if it's behind the camera
+pub fn to_screen(point: Vec3, view_projection: Mat4, screen_size: Vec2) -> Option<Vec2> {
+    let clip = view_projection * point.extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = vec2(clip.x, clip.y) / clip.w;
+    Some(vec2(ndc.x + 1.0, 1.0 - ndc.y) * screen_size / 2.0)
+}
+
+/// Parses a UTC date of the form `YYYY-MM-DD`, optionally followed by a
+/// time as `THH:MM:SS`, to seconds since the Unix epoch.
+pub fn parse_date(date: &str) -> Option<f64> {
+    let (date, time) = date.split_once('T').unwrap_or((date, "00:00:00"));
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>());
+    let (year, month, day) = (
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+    );
+    let mut time_parts = time.splitn(3, ':').map(|p| p.parse::<f64>());
+    let (hours, minutes, seconds) = (
+        time_parts.next()?.ok()?,
+        time_parts.next()?.ok()?,
+        time_parts.next()?.ok()?,
+    );
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day) as f64;
+    Some(days * SECONDS_PER_DAY as f64 + hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time
+pub fn format_date(seconds: f64) -> String {
+    let total = seconds.floor() as i64;
+    let (year, month, day) = civil_from_days(total.div_euclid(SECONDS_PER_DAY));
+    let time = total.rem_euclid(SECONDS_PER_DAY);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Units the details of the selected body are shown in, which default to
+/// SI with distances in kilometres. Simulations in other units set these
+/// to match their `constants`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudUnits {
+    /// Name of the simulation's unit of mass
+    pub mass: String,
+    /// Name of the unit distances are shown in
+    pub distance: String,
+    /// Simulation distance units in one of the units distances are shown in
+    pub distance_scale: f32,
+    /// Name of the simulation's unit of time
+    pub time: String,
+}
+
+impl Default for HudUnits {
+    fn default() -> Self {
+        Self {
+            mass: String::from("kg"),
+            distance: String::from("km"),
+            distance_scale: 1000.0,
+            time: String::from("s"),
+        }
+    }
+}
+
+/// Overlay showing the name of each body next to it, and details of a
+/// selected body.
+pub struct Hud {
+    pub visible: bool,
+    labels: Vec<String>,
+    selected: Option<usize>,
+    /// Body the distance of the selected body is measured from
+    reference: usize,
+    /// Calendar time at t = 0, in seconds since the Unix epoch
+    epoch: Option<f64>,
+    units: HudUnits,
+}
+
+impl Hud {
+    pub fn new(labels: Vec<String>, epoch: Option<f64>, units: HudUnits) -> Self {
+        Self {
+            visible: true,
+            labels,
+            selected: None,
+            reference: 0,
+            epoch,
+            units,
+        }
+    }
+
+    /// Calendar date at simulated time t, if an epoch was configured
+    pub fn date(&self, t: f32) -> Option<String> {
+        self.epoch.map(|epoch| format_date(epoch + t as f64))
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.selected
+            .and_then(|i| self.labels.get(i))
+            .map(String::as_str)
+    }
+
+    pub fn select(&mut self, label: Option<&str>) {
+        self.selected = label.and_then(|label| self.labels.iter().position(|l| l == label));
+    }
+
+    /// Selects the next body, with no body selected after the last one
+    pub fn cycle_selected(&mut self) {
+        self.selected = match self.selected {
+            None if !self.labels.is_empty() => Some(0),
+            Some(i) if i + 1 < self.labels.len() => Some(i + 1),
+            _ => None,
+        };
+    }
+
+    pub fn cycle_reference(&mut self) {
+        self.reference = (self.reference + 1) % self.labels.len().max(1);
+    }
+
+    /// Queues the label of each body beside its projected position, in
+    /// the colour of its model.
+    pub fn queue_labels(
+        &self,
+        text: &mut TextRenderer,
+        body_state_map: &BodyStateMap,
+        models: &HashMap<String, Model>,
+        view_projection: Mat4,
+        screen_size: Vec2,
+        scale: &SceneScale,
+    ) {
+        for model in models.values() {
+            let color = model.overlay_color.extend(1.0).to_array();
+            for label in &model.bodies {
+                let body_state = match body_state_map.get(label) {
+                    Some(body_state) => body_state,
+                    None => continue,
+                };
+                let position = scale.position(&body_state.pos);
+                let screen = match to_screen(position, view_projection, screen_size) {
+                    Some(screen) => screen,
+                    None => continue,
+                };
+                let offset = text.line_height() / 2.0;
+                text.queue(label, screen.x + offset, screen.y - offset, color);
+            }
+        }
+    }
+
+    /// Lines describing the selected body, if any
+    pub fn panel(&self, body_state_map: &BodyStateMap) -> Vec<String> {
+        let label = match self.selected() {
+            Some(label) => label,
+            None => return vec![],
+        };
+        let body_state = match body_state_map.get(label) {
+            Some(body_state) => body_state,
+            None => return vec![],
+        };
+        let units = &self.units;
+        let mut lines = vec![
+            label.to_string(),
+            format!("mass      {:.4e} {}", body_state.mass, units.mass),
+            format!(
+                "speed     {:.3} {}/{}",
+                body_state.velocity.magnitude() / units.distance_scale,
+                units.distance,
+                units.time
+            ),
+        ];
+        let reference = self.labels.get(self.reference);
+        if let Some((reference, other)) = reference
+            .filter(|r| r.as_str() != label)
+            .and_then(|r| body_state_map.get(r).map(|b| (r, b)))
+        {
+            lines.push(format!(
+                "distance  {:.0} {} from {}",
+                body_state.pos.distance(&other.pos) / units.distance_scale,
+                units.distance,
+                reference
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector3;
+    use crate::simulation::Body;
+
+    #[test]
+    fn dates_round_trip_through_seconds() {
+        assert_eq!(parse_date("1970-01-01"), Some(0.0));
+        assert_eq!(parse_date("2000-01-01T12:00:00"), Some(946_728_000.0));
+        assert_eq!(format_date(946_728_000.0), "2000-01-01 12:00:00 UTC");
+        let leap_day = parse_date("2024-02-29T23:59:59").unwrap();
+        assert_eq!(format_date(leap_day), "2024-02-29 23:59:59 UTC");
+        assert_eq!(format_date(leap_day + 1.0), "2024-03-01 00:00:00 UTC");
+        assert_eq!(parse_date("2024-13-01"), None);
+    }
+
+    #[test]
+    fn panels_show_values_in_the_configured_units() {
+        let mut body_state_map = BodyStateMap::new();
+        for (label, x) in [("Sun", 0.0), ("Earth", 1.0)] {
+            let body = Body::new(
+                String::from(label),
+                3e-6,
+                0.01,
+                Vector3::new(x, 0.0, 0.0),
+                Vector3::new(0.0, 0.0, 5.0),
+                Default::default(),
+            );
+            body_state_map.insert(String::from(label), (&body).into());
+        }
+        let units: HudUnits =
+            serde_yaml::from_str("{mass: Msun, distance: AU, distance_scale: 1.0, time: yr}")
+                .unwrap();
+        let mut hud = Hud::new(
+            vec![String::from("Sun"), String::from("Earth")],
+            None,
+            units,
+        );
+        hud.select(Some("Earth"));
+        assert_eq!(
+            hud.panel(&body_state_map),
+            vec![
+                "Earth",
+                "mass      3.0000e-6 Msun",
+                "speed     5.000 AU/yr",
+                "distance  1 AU from Sun",
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+// Lights and shadow casters shared by the viewer and the headless renderer.
+// `visibility` mirrors the shadowing in shaders/geo.frag.
+use glam::{Vec3, Vec4, Vec4Swizzles};
+
+use super::scale::SceneScale;
+use super::{BodyStateMap, LIGHT_POS};
+
+pub const MAX_LIGHTS: usize = 4;
+pub const MAX_OCCLUDERS: usize = 16;
+
+/// Spheres in the scene that emit light or cast shadows, each as its
+/// centre in xyz and its radius in w
+#[derive(Debug, Default)]
+pub struct SceneLighting {
+    pub lights: Vec<Vec4>,
+    pub occluders: Vec<Vec4>,
+}
+
+impl SceneLighting {
+    /// Makes emissive bodies the lights of the scene, falling back to a
+    /// fixed point light if there are none, and the rest of the bodies
+    /// shadow casters if `shadows` is set. Only the largest bodies cast
+    /// shadows, up to `MAX_OCCLUDERS`.
+    pub fn new(
+        body_state_map: &BodyStateMap,
+        scale: &SceneScale,
+        is_emissive: impl Fn(&str) -> bool,
+        shadows: bool,
+    ) -> Self {
+        let mut lighting = Self::default();
+        for (label, body_state) in body_state_map {
+            let sphere = scale
+                .position(&body_state.pos)
+                .extend(scale.size(body_state.diameter));
+            if is_emissive(label) {
+                lighting.lights.push(sphere);
+            } else if shadows {
+                lighting.occluders.push(sphere);
+            }
+        }
+        if lighting.lights.is_empty() {
+            lighting.lights.push(LIGHT_POS.extend(0.0));
+        }
+        lighting.lights.truncate(MAX_LIGHTS);
+        lighting
+            .occluders
+            .sort_by(|a, b| b.w.partial_cmp(&a.w).unwrap_or(std::cmp::Ordering::Equal));
+        lighting.occluders.truncate(MAX_OCCLUDERS);
+        lighting
+    }
+
+    /// Padded arrays of the lights and occluders, as passed to the shader
+    pub fn uniform_arrays(&self) -> ([[f32; 4]; MAX_LIGHTS], [[f32; 4]; MAX_OCCLUDERS]) {
+        let mut lights = [[0.0; 4]; MAX_LIGHTS];
+        for (uniform, light) in lights.iter_mut().zip(&self.lights) {
+            *uniform = light.to_array();
+        }
+        let mut occluders = [[0.0; 4]; MAX_OCCLUDERS];
+        for (uniform, occluder) in occluders.iter_mut().zip(&self.occluders) {
+            *uniform = occluder.to_array();
+        }
+        (lights, occluders)
+    }
+}
+
+/// Fraction of a spherical light visible from a point, with the occluders
+/// treated as discs covering part of the light's disc. Occluders the
+/// point is on or inside, such as its own body, are ignored.
+pub fn visibility(point: Vec3, light: Vec4, occluders: &[Vec4]) -> f32 {
+    let to_light = light.xyz() - point;
+    let light_distance = to_light.length();
+    let light_size = (light.w / light_distance).min(1.0).asin();
+    let mut visible = 1.0;
+    for occluder in occluders {
+        let to_occluder = occluder.xyz() - point;
+        let occluder_distance = to_occluder.length();
+        if occluder_distance <= occluder.w * 1.001 || occluder_distance >= light_distance {
+            continue;
+        }
+        let occluder_size = (occluder.w / occluder_distance).asin();
+        let separation = (to_light.dot(to_occluder) / (light_distance * occluder_distance))
+            .clamp(-1.0, 1.0)
+            .acos();
+        visible *= 1.0 - coverage(light_size, occluder_size, separation);
+    }
+    visible
+}
+
+/// Approximate fraction of a disc of angular radius `a` covered by a disc
+/// of angular radius `b` whose centre is `separation` away
+fn coverage(a: f32, b: f32, separation: f32) -> f32 {
+    let full = if a > 0.0 {
+        (b * b / (a * a)).min(1.0)
+    } else {
+        1.0
+    };
+    let (inner, outer) = ((a - b).abs(), a + b);
+    if separation >= outer {
+        0.0
+    } else if separation <= inner {
+        full
+    } else {
+        full * (1.0 - smoothstep(inner, outer, separation))
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn occluders_between_point_and_light_cast_shadows() {
+        let light = Vec4::new(10.0, 0.0, 0.0, 1.0);
+        let point = Vec3::ZERO;
+        assert_eq!(visibility(point, light, &[]), 1.0);
+
+        let total = Vec4::new(2.0, 0.0, 0.0, 0.5);
+        assert_eq!(visibility(point, light, &[total]), 0.0);
+
+        let partial = Vec4::new(5.0, 0.5, 0.0, 0.3);
+        let visible = visibility(point, light, &[partial]);
+        assert!(visible > 0.0 && visible < 1.0);
+
+        let behind = Vec4::new(-2.0, 0.0, 0.0, 0.5);
+        assert_eq!(visibility(point, light, &[behind]), 1.0);
+    }
+}
@@ -0,0 +1,167 @@
+use glam::{Mat4, Vec3};
+use miniquad::{
+    Bindings, BlendFactor, BlendState, BlendValue, Buffer, BufferLayout, BufferType, Comparison,
+    Context, Equation, Pipeline, PipelineParams, PrimitiveType, Shader, ShaderMeta,
+    UniformBlockLayout, UniformDesc, UniformType, VertexAttribute, VertexFormat,
+};
+
+const VERTEX_SHADER: &str = include_str!("../shaders/line.vert");
+const FRAGMENT_SHADER: &str = include_str!("../shaders/line.frag");
+
+const INITIAL_CAPACITY: usize = 4096;
+
+#[repr(C)]
+struct LineVertex {
+    pos: Vec3,
+    color: [f32; 4],
+}
+
+#[repr(C)]
+struct LineUniforms {
+    view_projection: Mat4,
+}
+
+/// Draws coloured line segments in scene space. Lines are queued during a
+/// frame and drawn in a single call, depth tested against the bodies.
+pub struct LineRenderer {
+    pipeline: Pipeline,
+    bindings: Bindings,
+    /// Number of vertices the buffers currently have room for
+    capacity: usize,
+    vertices: Vec<LineVertex>,
+}
+
+impl LineRenderer {
+    pub fn new(context: &mut Context) -> Self {
+        let meta = ShaderMeta {
+            images: vec![],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![UniformDesc::new("view_projection", UniformType::Mat4)],
+            },
+        };
+        let shader = Shader::new(context, VERTEX_SHADER, FRAGMENT_SHADER, meta).unwrap();
+        let pipeline = Pipeline::with_params(
+            context,
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float3),
+                VertexAttribute::new("color", VertexFormat::Float4),
+            ],
+            shader,
+            PipelineParams {
+                primitive_type: PrimitiveType::Lines,
+                depth_test: Comparison::LessOrEqual,
+                depth_write: false,
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+        );
+
+        Self {
+            pipeline,
+            bindings: Self::create_bindings(context, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            vertices: Vec::new(),
+        }
+    }
+
+    fn create_bindings(context: &mut Context, capacity: usize) -> Bindings {
+        let vertex_buffer = Buffer::stream(
+            context,
+            BufferType::VertexBuffer,
+            capacity * std::mem::size_of::<LineVertex>(),
+        );
+        let indices: Vec<u32> = (0..capacity as u32).collect();
+        let index_buffer = Buffer::immutable(context, BufferType::IndexBuffer, &indices);
+        Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![],
+        }
+    }
+
+    /// Queues a single segment, blending between the colours of its ends
+    pub fn queue_segment(
+        &mut self,
+        from: Vec3,
+        to: Vec3,
+        from_color: [f32; 4],
+        to_color: [f32; 4],
+    ) {
+        self.vertices.push(LineVertex {
+            pos: from,
+            color: from_color,
+        });
+        self.vertices.push(LineVertex {
+            pos: to,
+            color: to_color,
+        });
+    }
+
+    /// Queues an arrow from one point to another, with a head made of four
+    /// short lines angled back from the tip.
+    pub fn queue_arrow(&mut self, from: Vec3, to: Vec3, color: [f32; 4]) {
+        let shaft = to - from;
+        let length = shaft.length();
+        if length <= 0.0 {
+            return;
+        }
+        self.queue_segment(from, to, color, color);
+        let direction = shaft / length;
+        let (a, b) = direction.any_orthonormal_pair();
+        let back = to - 0.2 * length * direction;
+        for side in [a, -a, b, -b] {
+            self.queue_segment(to, back + 0.1 * length * side, color, color);
+        }
+    }
+
+    /// Queues a connected line through the points. If `fade` is set, the
+    /// line fades in from transparent at the first point.
+    pub fn queue_strip(&mut self, points: &[Vec3], color: [f32; 4], fade: bool) {
+        let alpha = |i: usize| {
+            if fade {
+                color[3] * i as f32 / (points.len() - 1) as f32
+            } else {
+                color[3]
+            }
+        };
+        for (i, pair) in points.windows(2).enumerate() {
+            let (mut from_color, mut to_color) = (color, color);
+            from_color[3] = alpha(i);
+            to_color[3] = alpha(i + 1);
+            self.queue_segment(pair[0], pair[1], from_color, to_color);
+        }
+    }
+
+    /// Queues a line through the points that's drawn only between every
+    /// other pair of points.
+    pub fn queue_dashed(&mut self, points: &[Vec3], color: [f32; 4]) {
+        for pair in points.windows(2).step_by(2) {
+            self.queue_segment(pair[0], pair[1], color, color);
+        }
+    }
+
+    /// Draws and clears all queued lines. Must be called within a render
+    /// pass.
+    pub fn draw(&mut self, context: &mut Context, view_projection: Mat4) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        if self.vertices.len() > self.capacity {
+            self.bindings.vertex_buffers[0].delete();
+            self.bindings.index_buffer.delete();
+            self.capacity = self.vertices.len().next_power_of_two();
+            self.bindings = Self::create_bindings(context, self.capacity);
+        }
+        self.bindings.vertex_buffers[0].update(context, &self.vertices);
+        context.apply_pipeline(&self.pipeline);
+        context.apply_bindings(&self.bindings);
+        context.apply_uniforms(&LineUniforms { view_projection });
+        context.draw(0, self.vertices.len() as i32, 1);
+        self.vertices.clear();
+    }
+}
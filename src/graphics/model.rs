@@ -1,75 +1,228 @@
-use glam::{vec3, Mat4, Quat, Vec3};
-use image::io::Reader as ImageReader;
+use glam::{Mat4, Vec3};
+use image::{imageops, Rgba, RgbaImage};
 use miniquad::{
-    Bindings, Buffer, BufferType, Context, FilterMode, Texture, TextureFormat, TextureParams,
+    gl, Bindings, Buffer, BufferType, Context, FilterMode, Texture, TextureFormat, TextureParams,
     TextureWrap,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::math::{Distance, Vector2, Vector3};
 use std::f32::consts::PI;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use super::BodyStateMap;
+use super::lighting::{MAX_LIGHTS, MAX_OCCLUDERS};
+use super::obj;
+use super::scale::SceneScale;
+use super::{to_vec3, BodyStateMap};
 
+#[repr(C)]
 #[derive(Clone, Copy, Default)]
 pub struct Uniforms {
-    model: Mat4,
     pub view: Mat4,
     pub projection: Mat4,
-    pub normal_mat: Mat4,
     pub light_color: Vec3,
-    pub light_pos: Vec3,
+    emissive: f32,
+    double_sided: f32,
+    pub num_lights: i32,
+    pub num_occluders: i32,
+    pub lights: [[f32; 4]; MAX_LIGHTS],
+    pub occluders: [[f32; 4]; MAX_OCCLUDERS],
+}
+
+/// Per-instance attributes of each body drawn with a model
+#[repr(C)]
+struct Instance {
+    model: Mat4,
+    normal_mat: Mat4,
 }
 
 #[repr(C)]
 pub struct Vertex<T, U> {
-    pos: T,
-    normal: T,
-    tex_coord: U,
+    pub pos: T,
+    pub normal: T,
+    pub tex_coord: U,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// Vertices and triangle indices of a model's shape
+pub type Geometry = (Vec<Vertex<Vector3, Vector2>>, Vec<u32>);
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Shape {
     #[default]
     Sphere,
+    /// Sphere stretched along each axis by `axes`
+    Ellipsoid,
+    /// Flat annulus between `inner_radius` and `outer_radius` around the
+    /// body's equator, with the texture's alpha channel for transparency
+    Ring,
+    /// Dot of `point_size` pixels, for large numbers of small particles
+    Point,
+    /// Triangle mesh loaded from the OBJ file at `mesh`
+    Mesh,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Model {
     pub shape: Shape,
-    pub texture: PathBuf,
+    /// Image wrapped around the model, relative to the config root. Models
+    /// without one are drawn in `color`.
+    #[serde(default)]
+    pub texture: Option<PathBuf>,
+    /// Solid colour of an untextured model, as red, green and blue between
+    /// 0 and 1
+    #[serde(default)]
+    pub color: Option<Vector3>,
     pub bodies: Vec<String>,
+    /// Scale of an ellipsoid along the x, y and z axes relative to the body's
+    /// size, with y being the spin axis
+    #[serde(default)]
+    pub axes: Option<Vector3>,
+    /// Inner radius of a ring, in multiples of the body's size
+    #[serde(default)]
+    pub inner_radius: Option<f32>,
+    /// Outer radius of a ring, in multiples of the body's size
+    #[serde(default)]
+    pub outer_radius: Option<f32>,
+    /// Diameter of point sprites, in pixels
+    #[serde(default)]
+    pub point_size: Option<f32>,
+    /// OBJ file of a mesh relative to the config root, with coordinates in
+    /// multiples of the body's size
+    #[serde(default)]
+    pub mesh: Option<PathBuf>,
+    /// Number of past positions drawn behind each of the model's bodies
+    #[serde(default = "default_trail_length")]
+    pub trail_length: usize,
+    /// Whether the model's bodies give off light, being drawn unlit and
+    /// lighting the other bodies
+    #[serde(default)]
+    pub emissive: bool,
 
+    /// Colour the model's trails and other overlays are drawn in
+    #[serde(skip)]
+    pub overlay_color: Vec3,
     #[serde(skip)]
     bindings: Option<Bindings>,
     #[serde(skip)]
     num_indices: usize,
 }
 
+fn default_trail_length() -> usize {
+    500
+}
+
 impl Model {
+    pub fn point_size(&self) -> f32 {
+        self.point_size.unwrap_or(4.0)
+    }
+
+    /// Whether the model is drawn blended with what's behind it, after the
+    /// opaque models
+    pub fn is_transparent(&self) -> bool {
+        self.shape == Shape::Ring
+    }
+
+    /// Generates or loads the model's geometry, in multiples of the size of
+    /// its bodies. Point sprites have none, and meshes that can't be loaded
+    /// are replaced by a sphere.
+    pub fn geometry(&self, root_path: &Path) -> Geometry {
+        match self.shape {
+            Shape::Sphere | Shape::Ellipsoid => generate_uv_sphere(20, 24),
+            Shape::Ring => generate_ring(
+                self.inner_radius.unwrap_or(1.2),
+                self.outer_radius.unwrap_or(2.3),
+                64,
+            ),
+            Shape::Point => (vec![], vec![]),
+            Shape::Mesh => {
+                let path = match &self.mesh {
+                    Some(path) => path,
+                    None => {
+                        eprintln!("warning: mesh model has no mesh file, using a sphere");
+                        return generate_uv_sphere(20, 24);
+                    }
+                };
+                match obj::load(&root_path.join(path)) {
+                    Ok(geometry) => geometry,
+                    Err(err) => {
+                        eprintln!(
+                            "warning: failed to load mesh {}: {}, using a sphere",
+                            path.display(),
+                            err
+                        );
+                        generate_uv_sphere(20, 24)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Transform applied to the model's geometry before the body's own
+    pub fn shape_matrix(&self) -> Mat4 {
+        match (&self.shape, &self.axes) {
+            (Shape::Ellipsoid, Some(axes)) => Mat4::from_scale(to_vec3(axes)),
+            _ => Mat4::IDENTITY,
+        }
+    }
+
+    /// Decodes the model's texture image, relative to the config root, or
+    /// makes a single pixel image of its colour if it has none. Textures
+    /// that can't be loaded are replaced by a placeholder.
+    pub fn load_image(&self, root_path: &Path) -> RgbaImage {
+        let path = match &self.texture {
+            Some(path) => path,
+            None => {
+                let color = self.color.unwrap_or(Vector3::new(0.8, 0.8, 0.8));
+                let [r, g, b] = [color.x(), color.y(), color.z()]
+                    .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+                return RgbaImage::from_pixel(1, 1, Rgba([r, g, b, 255]));
+            }
+        };
+        match image::open(root_path.join(path)) {
+            Ok(img) => img.to_rgba8(),
+            Err(err) => {
+                eprintln!(
+                    "warning: failed to load texture {}: {}, using a placeholder",
+                    path.display(),
+                    err
+                );
+                placeholder_texture()
+            }
+        }
+    }
+
     pub fn load(&mut self, context: &mut Context, root_path: &PathBuf) {
-        let img_path = root_path.join(&self.texture);
-        let img = ImageReader::open(img_path).unwrap().decode().unwrap();
+        let img = self.load_image(root_path);
+        self.overlay_color = average_color(&img);
+        if self.shape == Shape::Point {
+            return;
+        }
         let texture_resource = Texture::from_data_and_format(
             context,
-            img.as_rgb8().unwrap(),
+            &img,
             TextureParams {
-                format: TextureFormat::RGB8,
+                format: TextureFormat::RGBA8,
                 wrap: TextureWrap::Repeat,
                 filter: FilterMode::Linear,
                 width: img.width(),
                 height: img.height(),
             },
         );
-        let (vertices, indices) = generate_uv_sphere(20, 24);
+        upload_mipmaps(&texture_resource, &img);
+        let (vertices, indices) = self.geometry(root_path);
         let geometry_vertex_buffer =
             Buffer::immutable(context, BufferType::VertexBuffer, &vertices);
         let index_buffer = Buffer::immutable(context, BufferType::IndexBuffer, &indices);
+        // Rewritten every frame with the transforms of the model's bodies
+        let instance_buffer = Buffer::stream(
+            context,
+            BufferType::VertexBuffer,
+            self.bodies.len() * std::mem::size_of::<Instance>(),
+        );
 
         self.bindings = Some(Bindings {
-            vertex_buffers: vec![geometry_vertex_buffer],
+            vertex_buffers: vec![geometry_vertex_buffer, instance_buffer],
             index_buffer: index_buffer,
             images: vec![texture_resource],
         });
@@ -81,33 +234,92 @@ impl Model {
         context: &mut Context,
         body_state_map: &BodyStateMap,
         uniforms: &Uniforms,
-        scale: f32,
+        scale: &SceneScale,
     ) {
-        for body_label in &self.bodies {
-            let body_state = body_state_map.get(body_label).unwrap();
-            let inst_scale = body_state.diameter;
-            let tilt_radians = body_state.tilt.to_radians();
-            let tilt_axis = vec3(0.0, 0.0, -1.0);
-            let rotation_axis = vec3(0.0, 1.0, 0.0);
-            let rotation = Quat::from_axis_angle(rotation_axis, body_state.rot);
-            let tilt = Quat::from_axis_angle(tilt_axis, tilt_radians);
-            let model_mat = Mat4::from_scale_rotation_translation(
-                inst_scale * Vec3::ONE / scale,
-                tilt * rotation,
-                vec3(
-                    body_state.pos.x() / scale,
-                    body_state.pos.y() / scale,
-                    body_state.pos.z() / scale,
-                ),
-            );
-            let mut unif = uniforms.clone();
-            unif.model = model_mat;
-            unif.normal_mat = (model_mat.inverse()).transpose();
+        let bindings = match &self.bindings {
+            Some(bindings) => bindings,
+            None => return,
+        };
+        let shape_matrix = self.shape_matrix();
+        let instances: Vec<Instance> = self
+            .bodies
+            .iter()
+            .filter_map(|label| body_state_map.get(label))
+            .map(|body_state| {
+                let model = body_state.model_matrix(scale) * shape_matrix;
+                Instance {
+                    model,
+                    normal_mat: model.inverse().transpose(),
+                }
+            })
+            .collect();
+        if instances.is_empty() {
+            return;
+        }
+        bindings.vertex_buffers[1].update(context, &instances);
 
-            context.apply_bindings(self.bindings.as_ref().unwrap());
-            context.apply_uniforms(&unif);
-            context.draw(0, self.num_indices as i32, 1);
+        let mut unif = *uniforms;
+        unif.emissive = if self.emissive { 1.0 } else { 0.0 };
+        unif.double_sided = if self.shape == Shape::Ring { 1.0 } else { 0.0 };
+        context.apply_bindings(bindings);
+        context.apply_uniforms(&unif);
+        context.draw(0, self.num_indices as i32, instances.len() as i32);
+    }
+}
+
+/// Average colour of an image, brightened so that overlays drawn in it
+/// stand out
+pub fn average_color(img: &RgbaImage) -> Vec3 {
+    let [r, g, b, _] = imageops::thumbnail(img, 1, 1).get_pixel(0, 0).0;
+    let [r, g, b] = [r, g, b].map(|c| c as f32);
+    Vec3::new(r, g, b) / r.max(g).max(b).max(1.0)
+}
+
+/// Magenta and black checkerboard drawn in place of missing textures
+fn placeholder_texture() -> RgbaImage {
+    RgbaImage::from_fn(64, 64, |x, y| match (x / 8 + y / 8) % 2 {
+        0 => Rgba([255, 0, 255, 255]),
+        _ => Rgba([0, 0, 0, 255]),
+    })
+}
+
+/// Uploads successively halved copies of the image as the texture's mipmap
+/// levels and samples between them, so that distant bodies don't shimmer.
+/// miniquad has no mipmap support, so this goes through GL directly, and
+/// restores the previously bound texture to keep miniquad's state cache
+/// valid.
+fn upload_mipmaps(texture: &Texture, img: &RgbaImage) {
+    const GL_TEXTURE_BINDING_2D: u32 = 0x8069;
+    unsafe {
+        let mut previous = 0;
+        gl::glActiveTexture(gl::GL_TEXTURE0);
+        gl::glGetIntegerv(GL_TEXTURE_BINDING_2D, &mut previous);
+        gl::glBindTexture(gl::GL_TEXTURE_2D, texture.gl_internal_id());
+
+        let mut level = img.clone();
+        let mut index = 0;
+        while level.width() > 1 || level.height() > 1 {
+            let (width, height) = ((level.width() / 2).max(1), (level.height() / 2).max(1));
+            level = imageops::resize(&level, width, height, imageops::FilterType::Triangle);
+            index += 1;
+            gl::glTexImage2D(
+                gl::GL_TEXTURE_2D,
+                index,
+                gl::GL_RGBA8 as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::GL_RGBA,
+                gl::GL_UNSIGNED_BYTE,
+                level.as_ptr() as *const _,
+            );
         }
+        gl::glTexParameteri(
+            gl::GL_TEXTURE_2D,
+            gl::GL_TEXTURE_MIN_FILTER,
+            gl::GL_LINEAR_MIPMAP_LINEAR as i32,
+        );
+        gl::glBindTexture(gl::GL_TEXTURE_2D, previous as u32);
     }
 }
 
@@ -180,3 +392,64 @@ pub fn generate_uv_sphere(
 
     (vertices, indices)
 }
+
+/// Generates a flat annulus in the xz plane with the given number of
+/// segments. Texture coordinates run from the inner to the outer edge
+/// along u, and around the ring along v.
+pub fn generate_ring(inner_radius: f32, outer_radius: f32, n_segments: u32) -> Geometry {
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    for segment in 0..=n_segments {
+        let percent_rotation = segment as f32 / n_segments as f32;
+        let theta = 2.0 * PI * percent_rotation;
+        let (x, z) = (theta.cos(), theta.sin());
+        for (radius, u) in [(inner_radius, 0.0), (outer_radius, 1.0)] {
+            vertices.push(Vertex {
+                pos: Vector3::new(radius * x, 0.0, radius * z),
+                normal: Vector3::new(0.0, 1.0, 0.0),
+                tex_coord: Vector2::new(u, percent_rotation),
+            });
+        }
+    }
+    for segment in 0..n_segments {
+        let inner = 2 * segment;
+        let outer = inner + 1;
+        indices.extend_from_slice(&[inner, outer, inner + 2]);
+        indices.extend_from_slice(&[inner + 2, outer, outer + 2]);
+    }
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_without_a_loadable_texture_still_get_an_image() {
+        let untextured: Model =
+            serde_yaml::from_str("shape: sphere\ncolor: [1.0, 0.5, 0.0]\nbodies: [Sun]").unwrap();
+        let img = untextured.load_image(Path::new("."));
+        assert_eq!(img.get_pixel(0, 0), &Rgba([255, 128, 0, 255]));
+        assert_eq!(average_color(&img), Vec3::new(1.0, 128.0 / 255.0, 0.0));
+
+        let missing: Model =
+            serde_yaml::from_str("shape: sphere\ntexture: missing.png\nbodies: [Sun]").unwrap();
+        assert_eq!(missing.load_image(Path::new(".")), placeholder_texture());
+    }
+
+    #[test]
+    fn meshes_that_cant_be_loaded_are_drawn_as_spheres() {
+        let sphere = generate_uv_sphere(20, 24);
+        for yaml in [
+            "shape: mesh\nbodies: [Ship]",
+            "shape: mesh\nmesh: missing.obj\nbodies: [Ship]",
+        ] {
+            let model: Model = serde_yaml::from_str(yaml).unwrap();
+            let (vertices, indices) = model.geometry(Path::new("."));
+            assert_eq!(
+                (vertices.len(), indices),
+                (sphere.0.len(), sphere.1.clone())
+            );
+        }
+    }
+}
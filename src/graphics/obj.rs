@@ -0,0 +1,139 @@
+// Minimal Wavefront OBJ reader, supporting the vertex, texture coordinate,
+// normal and face statements needed to draw a textured mesh. Materials,
+// groups and other statements are ignored.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use super::model::{Geometry, Vertex};
+use crate::math::{Distance, Vector2, Vector3};
+
+pub type ObjResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+pub fn load(path: &Path) -> ObjResult<Geometry> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Resolves a 1-based, or negative relative, OBJ index into a list
+fn resolve(index: &str, len: usize) -> ObjResult<usize> {
+    let index: i64 = index.parse()?;
+    let resolved = match index {
+        i if i > 0 => i - 1,
+        i if i < 0 => len as i64 + i,
+        _ => return Err("OBJ indices start at 1".into()),
+    };
+    match resolved {
+        i if (0..len as i64).contains(&i) => Ok(i as usize),
+        _ => Err(format!("OBJ index {} out of range", index).into()),
+    }
+}
+
+fn parse_floats<const N: usize>(parts: &mut std::str::SplitWhitespace) -> ObjResult<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in values.iter_mut() {
+        *value = parts.next().ok_or("missing OBJ coordinate")?.parse()?;
+    }
+    Ok(values)
+}
+
+/// Parses an OBJ file into indexed triangles. Polygons are triangulated as
+/// fans, and vertices without normals are given the average normal of the
+/// faces around them.
+pub fn parse(obj: &str) -> ObjResult<Geometry> {
+    let mut positions: Vec<Vector3> = Vec::new();
+    let mut tex_coords: Vec<Vector2> = Vec::new();
+    let mut normals: Vec<Vector3> = Vec::new();
+
+    let mut vertices: Vec<Vertex<Vector3, Vector2>> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    // Vertices already created for each combination of OBJ indices
+    let mut vertex_indices: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut missing_normals = false;
+
+    for line in obj.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("v") => positions.push(Vector3::from(parse_floats::<3>(&mut parts)?)),
+            Some("vt") => {
+                let [u, v] = parse_floats::<2>(&mut parts)?;
+                // OBJ texture coordinates start at the bottom of the image
+                tex_coords.push(Vector2::new(u, 1.0 - v));
+            }
+            Some("vn") => normals.push(Vector3::from(parse_floats::<3>(&mut parts)?)),
+            Some("f") => {
+                let mut face = Vec::new();
+                for corner in parts {
+                    let mut refs = corner.split('/');
+                    let position = resolve(refs.next().unwrap_or_default(), positions.len())?;
+                    let tex_coord = match refs.next() {
+                        Some(i) if !i.is_empty() => Some(resolve(i, tex_coords.len())?),
+                        _ => None,
+                    };
+                    let normal = match refs.next() {
+                        Some(i) if !i.is_empty() => Some(resolve(i, normals.len())?),
+                        _ => None,
+                    };
+                    missing_normals |= normal.is_none();
+                    let key = (position, tex_coord, normal);
+                    let index = *vertex_indices.entry(key).or_insert_with(|| {
+                        vertices.push(Vertex {
+                            pos: positions[position],
+                            normal: normal.map(|n| normals[n]).unwrap_or_default(),
+                            tex_coord: tex_coord.map(|t| tex_coords[t]).unwrap_or_default(),
+                        });
+                        vertices.len() as u32 - 1
+                    });
+                    face.push(index);
+                }
+                if face.len() < 3 {
+                    return Err("OBJ faces need at least three vertices".into());
+                }
+                for i in 1..face.len() - 1 {
+                    indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if missing_normals {
+        let mut accumulated = vec![Vector3::default(); vertices.len()];
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| &vertices[triangle[i] as usize].pos);
+            let face_normal = (b - a).cross(&(c - a));
+            for &i in triangle {
+                accumulated[i as usize] = &accumulated[i as usize] + &face_normal;
+            }
+        }
+        for (vertex, normal) in vertices.iter_mut().zip(accumulated) {
+            if vertex.normal == Vector3::default() {
+                vertex.normal = normal.normalize();
+            }
+        }
+    }
+
+    Ok((vertices, indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_a_quad_triangulates_and_computes_normals() {
+        let obj = "# unit square\n\
+                   v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                   vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                   f 1/1 2/2 3/3 -1/4\n";
+        let (vertices, indices) = parse(obj).unwrap();
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(vertices[2].tex_coord, Vector2::new(1.0, 0.0));
+        for vertex in &vertices {
+            assert_eq!(vertex.normal, Vector3::new(0.0, 0.0, 1.0));
+        }
+        assert!(parse("v 0 0 0\nf 1 2 3\n").is_err());
+    }
+}
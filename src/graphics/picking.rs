@@ -0,0 +1,115 @@
+use glam::{vec2, vec3, Mat4, Vec2, Vec3};
+
+use super::hud::to_screen;
+use super::scale::SceneScale;
+use super::BodyStateMap;
+
+/// Distance in screen pixels within which a body too small to click on
+/// directly is still picked
+const PICK_TOLERANCE: f32 = 8.0;
+
+/// A ray in scene space, with a normalized direction
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// Casts a ray from the camera through a point on the screen, in
+    /// pixels from the top left, by unprojecting it onto the near and far
+    /// planes.
+    pub fn from_screen(point: Vec2, screen_size: Vec2, view_projection: Mat4) -> Self {
+        let ndc = vec2(
+            2.0 * point.x / screen_size.x - 1.0,
+            1.0 - 2.0 * point.y / screen_size.y,
+        );
+        let inverse = view_projection.inverse();
+        let near = inverse.project_point3(vec3(ndc.x, ndc.y, -1.0));
+        let far = inverse.project_point3(vec3(ndc.x, ndc.y, 1.0));
+        Self {
+            origin: near,
+            direction: (far - near).normalize(),
+        }
+    }
+
+    /// Distance along the ray to its first intersection with a sphere
+    pub fn intersect_sphere(&self, center: Vec3, radius: f32) -> Option<f32> {
+        let to_center = center - self.origin;
+        let along = to_center.dot(self.direction);
+        let closest_sq = to_center.length_squared() - along * along;
+        let radius_sq = radius * radius;
+        if closest_sq > radius_sq {
+            return None;
+        }
+        let half_chord = (radius_sq - closest_sq).sqrt();
+        [along - half_chord, along + half_chord]
+            .into_iter()
+            .find(|d| *d >= 0.0)
+    }
+}
+
+/// Finds the body under a point on the screen. Bodies the ray passes
+/// through are preferred, nearest first, falling back to the body whose
+/// centre is closest on screen within a few pixels.
+pub fn pick(
+    point: Vec2,
+    screen_size: Vec2,
+    view_projection: Mat4,
+    body_state_map: &BodyStateMap,
+    scale: &SceneScale,
+    dpi_scale: f32,
+) -> Option<String> {
+    let ray = Ray::from_screen(point, screen_size, view_projection);
+    let mut hit: Option<(f32, &String)> = None;
+    let mut nearest: Option<(f32, &String)> = None;
+    for (label, body_state) in body_state_map {
+        let (size, _, center) = body_state
+            .model_matrix(scale)
+            .to_scale_rotation_translation();
+        if let Some(d) = ray.intersect_sphere(center, size.max_element()) {
+            if hit.is_none_or(|(closest, _)| d < closest) {
+                hit = Some((d, label));
+            }
+        }
+
+        let screen = match to_screen(center, view_projection, screen_size) {
+            Some(screen) => screen,
+            None => continue,
+        };
+        let distance = screen.distance(point);
+        if distance <= PICK_TOLERANCE * dpi_scale
+            && nearest.is_none_or(|(closest, _)| distance < closest)
+        {
+            nearest = Some((distance, label));
+        }
+    }
+    hit.or(nearest).map(|(_, label)| label.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_hit_spheres_in_front_of_them() {
+        let ray = Ray {
+            origin: Vec3::ZERO,
+            direction: Vec3::NEG_Z,
+        };
+        assert_eq!(ray.intersect_sphere(vec3(0.0, 0.0, -5.0), 1.0), Some(4.0));
+        assert_eq!(ray.intersect_sphere(vec3(0.0, 0.0, 0.0), 1.0), Some(1.0));
+        assert_eq!(ray.intersect_sphere(vec3(0.0, 0.0, 5.0), 1.0), None);
+        assert_eq!(ray.intersect_sphere(vec3(2.0, 0.0, -5.0), 1.0), None);
+    }
+
+    #[test]
+    fn screen_centre_casts_along_view_direction() {
+        let view = Mat4::look_at_rh(vec3(0.0, 0.0, 3.0), Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh_gl(1.0, 1.0, 0.01, 100.0);
+        let screen_size = vec2(800.0, 800.0);
+        let ray = Ray::from_screen(screen_size / 2.0, screen_size, projection * view);
+        assert!(ray.direction.abs_diff_eq(Vec3::NEG_Z, 1e-5));
+        assert!(ray.origin.abs_diff_eq(vec3(0.0, 0.0, 2.99), 1e-4));
+    }
+}
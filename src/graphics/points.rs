@@ -0,0 +1,134 @@
+use glam::{vec2, Mat4, Vec2, Vec3};
+use miniquad::{
+    Bindings, Buffer, BufferLayout, BufferType, Comparison, Context, Pipeline, PipelineParams,
+    Shader, ShaderMeta, UniformBlockLayout, UniformDesc, UniformType, VertexAttribute,
+    VertexFormat,
+};
+
+const VERTEX_SHADER: &str = include_str!("../shaders/point.vert");
+const FRAGMENT_SHADER: &str = include_str!("../shaders/point.frag");
+
+const INITIAL_CAPACITY: usize = 1024;
+
+const CORNERS: [Vec2; 4] = [
+    vec2(-1.0, -1.0),
+    vec2(1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, 1.0),
+];
+
+#[repr(C)]
+struct PointVertex {
+    pos: Vec3,
+    corner: Vec2,
+    size: f32,
+    color: [f32; 4],
+}
+
+#[repr(C)]
+struct PointUniforms {
+    view_projection: Mat4,
+    screen_size: Vec2,
+}
+
+/// Draws round sprites of a fixed size in pixels at points in scene space,
+/// for bodies too small and numerous to draw as meshes. Points are queued
+/// during a frame and drawn in a single call.
+pub struct PointRenderer {
+    pipeline: Pipeline,
+    bindings: Bindings,
+    /// Number of points the buffers currently have room for
+    capacity: usize,
+    vertices: Vec<PointVertex>,
+}
+
+impl PointRenderer {
+    pub fn new(context: &mut Context) -> Self {
+        let meta = ShaderMeta {
+            images: vec![],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("view_projection", UniformType::Mat4),
+                    UniformDesc::new("screen_size", UniformType::Float2),
+                ],
+            },
+        };
+        let shader = Shader::new(context, VERTEX_SHADER, FRAGMENT_SHADER, meta).unwrap();
+        let pipeline = Pipeline::with_params(
+            context,
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float3),
+                VertexAttribute::new("corner", VertexFormat::Float2),
+                VertexAttribute::new("size", VertexFormat::Float1),
+                VertexAttribute::new("color", VertexFormat::Float4),
+            ],
+            shader,
+            PipelineParams {
+                depth_test: Comparison::LessOrEqual,
+                depth_write: true,
+                ..Default::default()
+            },
+        );
+
+        Self {
+            pipeline,
+            bindings: Self::create_bindings(context, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            vertices: Vec::new(),
+        }
+    }
+
+    fn create_bindings(context: &mut Context, capacity: usize) -> Bindings {
+        let vertex_buffer = Buffer::stream(
+            context,
+            BufferType::VertexBuffer,
+            4 * capacity * std::mem::size_of::<PointVertex>(),
+        );
+        let indices: Vec<u32> = (0..capacity as u32)
+            .flat_map(|i| [0, 1, 2, 0, 2, 3].map(|corner| 4 * i + corner))
+            .collect();
+        let index_buffer = Buffer::immutable(context, BufferType::IndexBuffer, &indices);
+        Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![],
+        }
+    }
+
+    /// Queues a point `size` pixels across
+    pub fn queue_point(&mut self, pos: Vec3, size: f32, color: [f32; 4]) {
+        for corner in CORNERS {
+            self.vertices.push(PointVertex {
+                pos,
+                corner,
+                size,
+                color,
+            });
+        }
+    }
+
+    /// Draws and clears all queued points. Must be called within a render
+    /// pass.
+    pub fn draw(&mut self, context: &mut Context, view_projection: Mat4, screen_size: Vec2) {
+        let num_points = self.vertices.len() / 4;
+        if num_points == 0 {
+            return;
+        }
+        if num_points > self.capacity {
+            self.bindings.vertex_buffers[0].delete();
+            self.bindings.index_buffer.delete();
+            self.capacity = num_points.next_power_of_two();
+            self.bindings = Self::create_bindings(context, self.capacity);
+        }
+        self.bindings.vertex_buffers[0].update(context, &self.vertices);
+        context.apply_pipeline(&self.pipeline);
+        context.apply_bindings(&self.bindings);
+        context.apply_uniforms(&PointUniforms {
+            view_projection,
+            screen_size,
+        });
+        context.draw(0, 6 * num_points as i32, 1);
+        self.vertices.clear();
+    }
+}
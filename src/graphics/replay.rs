@@ -0,0 +1,216 @@
+use miniquad::KeyCode;
+use std::collections::HashMap;
+
+use super::BodyStateMap;
+use crate::math::Quaternion;
+use crate::simulation::{Attitude, Simulation, SpinCharacteristics};
+use crate::trajectory::Trajectory;
+
+// Display frames per second the default playback rate is based on
+const NOMINAL_FPS: f32 = 60.0;
+
+/// Plays back a recorded trajectory, interpolating between its frames.
+pub struct Replay {
+    trajectory: Trajectory,
+    // Used to derive orientations when the recording doesn't include them
+    spins: HashMap<String, SpinCharacteristics<3>>,
+    t: f32,
+    /// Simulated seconds played back per real second, negative when
+    /// playing backwards
+    rate: f32,
+    looping: bool,
+}
+
+impl Replay {
+    pub fn new(trajectory: Trajectory, simulation: &Simulation<3>) -> Self {
+        let spins = simulation
+            .bodies()
+            .iter()
+            .map(|b| (b.label.clone(), b.spin))
+            .collect();
+        let frames = &trajectory.frames;
+        let t = frames.first().map(|f| f.t).unwrap_or_default();
+        // Default to showing one recorded frame per display frame
+        let rate = match frames.len() {
+            0 | 1 => 1.0,
+            n => (frames[n - 1].t - t) / (n - 1) as f32 * NOMINAL_FPS,
+        };
+        Self {
+            trajectory,
+            spins,
+            t,
+            rate,
+            looping: true,
+        }
+    }
+
+    pub fn t(&self) -> f32 {
+        self.t
+    }
+
+    fn start(&self) -> f32 {
+        self.trajectory
+            .frames
+            .first()
+            .map(|f| f.t)
+            .unwrap_or_default()
+    }
+
+    fn end(&self) -> f32 {
+        self.trajectory
+            .frames
+            .last()
+            .map(|f| f.t)
+            .unwrap_or_default()
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub fn scale_rate(&mut self, factor: f32) {
+        self.rate *= factor;
+    }
+
+    pub fn reverse(&mut self) {
+        self.rate = -self.rate;
+    }
+
+    /// Advances playback by `dt` seconds of real time
+    pub fn advance(&mut self, dt: f32) {
+        self.seek(self.t + dt * self.rate);
+    }
+
+    /// Moves playback by whole recorded frames, in the direction of play
+    pub fn step_frames(&mut self, frames: usize) {
+        let n = self.trajectory.frames.len();
+        if n == 0 {
+            return;
+        }
+        let current = self
+            .trajectory
+            .frames
+            .partition_point(|f| f.t <= self.t)
+            .saturating_sub(1) as i64;
+        let offset = frames as i64 * self.rate.signum() as i64;
+        let target = if self.looping {
+            (current + offset).rem_euclid(n as i64)
+        } else {
+            (current + offset).clamp(0, n as i64 - 1)
+        };
+        self.t = self.trajectory.frames[target as usize].t;
+    }
+
+    /// Moves playback to time t, wrapping around if looping and otherwise
+    /// stopping at either end of the recording.
+    pub fn seek(&mut self, t: f32) {
+        let (start, end) = (self.start(), self.end());
+        let duration = end - start;
+        self.t = if self.looping && duration > 0.0 {
+            start + (t - start).rem_euclid(duration)
+        } else {
+            t.clamp(start, end)
+        };
+    }
+
+    /// Handles scrubbing and looping controls, returning whether the key
+    /// was used.
+    pub fn handle_key(&mut self, keycode: KeyCode) -> bool {
+        let scrub = (self.end() - self.start()) / 100.0;
+        match keycode {
+            KeyCode::Left => self.seek(self.t - scrub),
+            KeyCode::Right => self.seek(self.t + scrub),
+            KeyCode::Home => self.seek(self.start()),
+            KeyCode::L => self.looping = !self.looping,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Sets the positions and rotations of the bodies at the current time
+    pub fn update_body_states(&self, body_state_map: &mut BodyStateMap) {
+        let frames = &self.trajectory.frames;
+        if frames.is_empty() {
+            return;
+        }
+        let next = frames
+            .partition_point(|f| f.t <= self.t)
+            .min(frames.len() - 1);
+        let prev = next.saturating_sub(1);
+        let (f0, f1) = (&frames[prev], &frames[next]);
+        let alpha = match f1.t - f0.t {
+            span if span > 0.0 => ((self.t - f0.t) / span).clamp(0.0, 1.0),
+            _ => 0.0,
+        };
+
+        for (i, label) in self.trajectory.labels.iter().enumerate() {
+            let body_state = match body_state_map.get_mut(label) {
+                Some(body_state) => body_state,
+                None => continue,
+            };
+            let (p0, p1) = (&f0.positions[i], &f1.positions[i]);
+            body_state.pos = p0 + &(alpha * &(p1 - p0));
+            // Velocities aren't in every recording, so estimate them
+            if f1.t > f0.t {
+                body_state.velocity = &(p1 - p0) / (f1.t - f0.t);
+            }
+            let spin = self.spins.get(label);
+            body_state.orientation = match (&f0.orientations, &f1.orientations, spin) {
+                (Some(q0), Some(q1), _) => q0[i].slerp(&q1[i], alpha),
+                // Rotate steadily from the initial attitude
+                (_, _, Some(spin)) => {
+                    let attitude = Attitude::from(spin);
+                    let rotation = Quaternion::from_rotation_vector(
+                        &((self.t - self.start()) * &attitude.angular_velocity),
+                    );
+                    rotation * attitude.orientation
+                }
+                _ => continue,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectory::TrajectoryFrame;
+
+    fn replay() -> Replay {
+        let frames = (0..4)
+            .map(|i| TrajectoryFrame {
+                t: i as f32,
+                positions: vec![],
+                orientations: None,
+            })
+            .collect();
+        let trajectory = Trajectory {
+            labels: vec![],
+            frames,
+        };
+        Replay::new(trajectory, &Simulation::new(None, None, None))
+    }
+
+    #[test]
+    fn stepping_frames_wraps_around_when_looping() {
+        let mut replay = replay();
+        replay.step_frames(5);
+        assert_eq!(replay.t(), 1.0);
+        replay.reverse();
+        replay.step_frames(3);
+        assert_eq!(replay.t(), 2.0);
+    }
+
+    #[test]
+    fn stepping_frames_stops_at_the_ends_when_not_looping() {
+        let mut replay = replay();
+        replay.handle_key(KeyCode::L);
+        replay.step_frames(5);
+        assert_eq!(replay.t(), 3.0);
+        replay.reverse();
+        replay.step_frames(2);
+        assert_eq!(replay.t(), 1.0);
+        replay.step_frames(2);
+        assert_eq!(replay.t(), 0.0);
+    }
+}
@@ -0,0 +1,191 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use super::to_vec3;
+use crate::math::{Distance, Vector3};
+
+/// Distance from the origin, in scene units, that auto-fitting places the
+/// furthest body at, which keeps it in view of the default camera
+const FIT_EXTENT: f32 = 0.15;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMapping {
+    /// Scene distances are proportional to simulation distances
+    #[default]
+    Linear,
+    /// Distances from the centre of the scene are compressed logarithmically,
+    /// so that close and distant bodies are both visible
+    Logarithmic,
+}
+
+impl DistanceMapping {
+    fn toggled(self) -> Self {
+        match self {
+            DistanceMapping::Linear => DistanceMapping::Logarithmic,
+            DistanceMapping::Logarithmic => DistanceMapping::Linear,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScaleConfig {
+    /// Simulation distance corresponding to one unit in the scene. Fitted
+    /// to the initial positions of the bodies if not given.
+    pub scale: Option<f32>,
+    pub mapping: DistanceMapping,
+    /// Factor the radii of the bodies are multiplied by
+    pub radius_exaggeration: f32,
+}
+
+impl Default for ScaleConfig {
+    fn default() -> Self {
+        Self {
+            scale: None,
+            mapping: DistanceMapping::Linear,
+            radius_exaggeration: 1.0,
+        }
+    }
+}
+
+/// Maps simulation positions and sizes to the scene that's drawn.
+#[derive(Debug, Clone)]
+pub struct SceneScale {
+    /// Simulation position at the centre of the scene
+    center: Vector3,
+    /// Simulation distance per scene unit
+    scale: f32,
+    pub mapping: DistanceMapping,
+    /// Simulation distance from the centre at which logarithmic mapping
+    /// starts to compress distances
+    log_reference: f32,
+    /// Scene units per natural log of distance when mapped logarithmically
+    log_factor: f32,
+    pub radius_exaggeration: f32,
+}
+
+impl SceneScale {
+    /// Linear scale centred on the origin, with bodies at their true size
+    pub fn linear(scale: f32) -> Self {
+        Self {
+            center: Vector3::default(),
+            scale,
+            mapping: DistanceMapping::Linear,
+            log_reference: scale,
+            log_factor: 1.0,
+            radius_exaggeration: 1.0,
+        }
+    }
+
+    pub fn new(config: &ScaleConfig, positions: &[Vector3]) -> Self {
+        let mut scene_scale = match config.scale {
+            Some(scale) => {
+                let mut scene_scale = Self::linear(scale);
+                scene_scale.fit_log(positions);
+                scene_scale
+            }
+            None => {
+                let mut scene_scale = Self::linear(1.0);
+                scene_scale.fit(positions);
+                scene_scale
+            }
+        };
+        scene_scale.mapping = config.mapping;
+        scene_scale.radius_exaggeration = config.radius_exaggeration;
+        scene_scale
+    }
+
+    /// Centres the scene on the bounding box of the positions and scales it
+    /// so that they all fit in view.
+    pub fn fit(&mut self, positions: &[Vector3]) {
+        if positions.is_empty() {
+            return;
+        }
+        let mut min = positions[0];
+        let mut max = positions[0];
+        for p in positions {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        self.center = 0.5 * &(&min + &max);
+        let furthest = positions
+            .iter()
+            .map(|p| p.distance(&self.center))
+            .fold(0.0, f32::max);
+        if furthest > 0.0 {
+            self.scale = furthest / FIT_EXTENT;
+        }
+        self.fit_log(positions);
+    }
+
+    /// Fits the logarithmic mapping so that the furthest of the positions
+    /// from the centre is at the same distance as when mapped linearly.
+    fn fit_log(&mut self, positions: &[Vector3]) {
+        let distances: Vec<f32> = positions.iter().map(|p| p.distance(&self.center)).collect();
+        let furthest = distances.iter().cloned().fold(0.0, f32::max);
+        if furthest <= 0.0 {
+            return;
+        }
+        // Compress distances beyond the closest body to the centre, or a
+        // small fraction of the extent if a body is at the centre
+        let closest = distances
+            .iter()
+            .cloned()
+            .filter(|d| *d > furthest * 1e-3)
+            .fold(furthest, f32::min);
+        self.log_reference = closest;
+        self.log_factor = furthest / self.scale / (furthest / closest).ln_1p();
+    }
+
+    pub fn toggle_mapping(&mut self) {
+        self.mapping = self.mapping.toggled();
+    }
+
+    /// Position in the scene of a simulation position
+    pub fn position(&self, p: &Vector3) -> Vec3 {
+        let offset = to_vec3(&(p - &self.center));
+        match self.mapping {
+            DistanceMapping::Linear => offset / self.scale,
+            DistanceMapping::Logarithmic => {
+                let distance = offset.length();
+                if distance == 0.0 {
+                    return Vec3::ZERO;
+                }
+                offset / distance * self.log_factor * (distance / self.log_reference).ln_1p()
+            }
+        }
+    }
+
+    /// Size in the scene of a simulation length, such as a body's diameter.
+    /// Sizes aren't mapped logarithmically, as that would distort shapes.
+    pub fn size(&self, length: f32) -> f32 {
+        length * self.radius_exaggeration / self.scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fitting_centres_and_scales_the_bodies() {
+        let positions = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(4.0, 0.0, 0.0)];
+        let mut scene_scale = SceneScale::linear(1.0);
+        scene_scale.fit(&positions);
+        assert_eq!(
+            scene_scale.position(&positions[0]),
+            Vec3::new(-FIT_EXTENT, 0.0, 0.0)
+        );
+        assert_eq!(
+            scene_scale.position(&positions[1]),
+            Vec3::new(FIT_EXTENT, 0.0, 0.0)
+        );
+
+        scene_scale.toggle_mapping();
+        let outer = scene_scale.position(&positions[1]);
+        assert!((outer.x - FIT_EXTENT).abs() < 1e-6);
+    }
+}
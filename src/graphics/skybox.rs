@@ -0,0 +1,98 @@
+use glam::{Mat3, Mat4};
+use miniquad::{
+    Bindings, Buffer, BufferLayout, BufferType, Context, FilterMode, Pipeline, PipelineParams,
+    Shader, ShaderMeta, Texture, TextureFormat, TextureParams, TextureWrap, UniformBlockLayout,
+    UniformDesc, UniformType, VertexAttribute, VertexFormat,
+};
+use std::path::Path;
+
+use super::model::generate_uv_sphere;
+use crate::math::Vector3;
+
+const VERTEX_SHADER: &str = include_str!("../shaders/skybox.vert");
+const FRAGMENT_SHADER: &str = include_str!("../shaders/skybox.frag");
+
+#[repr(C)]
+struct SkyboxUniforms {
+    view_projection: Mat4,
+}
+
+/// Equirectangular image of the sky drawn around the camera, behind
+/// everything else in the scene.
+pub struct Skybox {
+    pipeline: Pipeline,
+    bindings: Bindings,
+    num_indices: usize,
+}
+
+impl Skybox {
+    /// Loads the sky image at `path`, warning and returning nothing if it
+    /// can't be loaded.
+    pub fn load(context: &mut Context, path: &Path) -> Option<Self> {
+        let img = match image::open(path) {
+            Ok(img) => img.to_rgba8(),
+            Err(err) => {
+                eprintln!("warning: failed to load skybox {}: {}", path.display(), err);
+                return None;
+            }
+        };
+        let texture = Texture::from_data_and_format(
+            context,
+            &img,
+            TextureParams {
+                format: TextureFormat::RGBA8,
+                wrap: TextureWrap::Repeat,
+                filter: FilterMode::Linear,
+                width: img.width(),
+                height: img.height(),
+            },
+        );
+
+        let meta = ShaderMeta {
+            images: vec!["sky".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![UniformDesc::new("view_projection", UniformType::Mat4)],
+            },
+        };
+        let shader = Shader::new(context, VERTEX_SHADER, FRAGMENT_SHADER, meta).unwrap();
+        // Without depth writes the sky is also drawn without depth testing,
+        // so the bodies drawn after it cover it
+        let pipeline = Pipeline::with_params(
+            context,
+            &[BufferLayout::default()],
+            &[VertexAttribute::new("pos", VertexFormat::Float3)],
+            shader,
+            PipelineParams {
+                depth_write: false,
+                ..Default::default()
+            },
+        );
+
+        let (vertices, indices) = generate_uv_sphere(20, 24);
+        let positions: Vec<Vector3> = vertices.into_iter().map(|v| v.pos).collect();
+        let vertex_buffer = Buffer::immutable(context, BufferType::VertexBuffer, &positions);
+        let index_buffer = Buffer::immutable(context, BufferType::IndexBuffer, &indices);
+
+        Some(Self {
+            pipeline,
+            bindings: Bindings {
+                vertex_buffers: vec![vertex_buffer],
+                index_buffer,
+                images: vec![texture],
+            },
+            num_indices: indices.len(),
+        })
+    }
+
+    /// Draws the sky as seen with the rotation of the view. Must be called
+    /// within a render pass, before anything else is drawn.
+    pub fn draw(&self, context: &mut Context, view: Mat4, projection: Mat4) {
+        let rotation = Mat4::from_mat3(Mat3::from_mat4(view));
+        context.apply_pipeline(&self.pipeline);
+        context.apply_bindings(&self.bindings);
+        context.apply_uniforms(&SkyboxUniforms {
+            view_projection: projection * rotation,
+        });
+        context.draw(0, self.num_indices as i32, 1);
+    }
+}
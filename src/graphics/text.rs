@@ -0,0 +1,180 @@
+use miniquad::{
+    Bindings, BlendFactor, BlendState, BlendValue, Buffer, BufferLayout, BufferType, Comparison,
+    Context, Equation, FilterMode, Pipeline, PipelineParams, Shader, ShaderMeta, Texture,
+    TextureFormat, TextureParams, TextureWrap, UniformBlockLayout, UniformDesc, UniformType,
+    VertexAttribute, VertexFormat,
+};
+
+use super::font::{self, ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+const VERTEX_SHADER: &str = include_str!("../shaders/text.vert");
+const FRAGMENT_SHADER: &str = include_str!("../shaders/text.frag");
+
+const MAX_GLYPHS: usize = 4096;
+const FIRST_CHAR: u8 = b' ';
+const NUM_CHARS: usize = 95;
+// Each glyph occupies a cell one pixel larger than it in both dimensions,
+// so that linear sampling never bleeds between neighbours.
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+#[repr(C)]
+struct TextVertex {
+    pos: [f32; 2],
+    tex_coord: [f32; 2],
+    color: [f32; 4],
+}
+
+#[repr(C)]
+struct TextUniforms {
+    screen_size: [f32; 2],
+}
+
+/// Draws screen-space text with the built-in bitmap font. Text is queued
+/// during a frame and drawn in a single call.
+pub struct TextRenderer {
+    pipeline: Pipeline,
+    bindings: Bindings,
+    vertices: Vec<TextVertex>,
+    /// Size in screen pixels of a single font pixel
+    pub scale: f32,
+}
+
+impl TextRenderer {
+    pub fn new(context: &mut Context) -> Self {
+        let atlas_width = CELL_WIDTH * NUM_CHARS;
+        let mut atlas = vec![0u8; atlas_width * CELL_HEIGHT * 4];
+        for i in 0..NUM_CHARS {
+            let c = (FIRST_CHAR + i as u8) as char;
+            for (x, y) in font::pixels(&c.to_string()) {
+                let offset = (y * atlas_width + i * CELL_WIDTH + x) * 4;
+                atlas[offset..offset + 4].copy_from_slice(&[255; 4]);
+            }
+        }
+        let texture = Texture::from_data_and_format(
+            context,
+            &atlas,
+            TextureParams {
+                format: TextureFormat::RGBA8,
+                wrap: TextureWrap::Clamp,
+                filter: FilterMode::Nearest,
+                width: atlas_width as u32,
+                height: CELL_HEIGHT as u32,
+            },
+        );
+
+        let mut indices: Vec<u16> = Vec::with_capacity(MAX_GLYPHS * 6);
+        for i in 0..MAX_GLYPHS as u16 {
+            let v = i * 4;
+            indices.extend_from_slice(&[v, v + 1, v + 2, v, v + 2, v + 3]);
+        }
+        let vertex_buffer = Buffer::stream(
+            context,
+            BufferType::VertexBuffer,
+            MAX_GLYPHS * 4 * std::mem::size_of::<TextVertex>(),
+        );
+        let index_buffer = Buffer::immutable(context, BufferType::IndexBuffer, &indices);
+        let bindings = Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![texture],
+        };
+
+        let meta = ShaderMeta {
+            images: vec!["glyphs".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![UniformDesc::new("screen_size", UniformType::Float2)],
+            },
+        };
+        let shader = Shader::new(context, VERTEX_SHADER, FRAGMENT_SHADER, meta).unwrap();
+        let pipeline = Pipeline::with_params(
+            context,
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float2),
+                VertexAttribute::new("tex_coord", VertexFormat::Float2),
+                VertexAttribute::new("color", VertexFormat::Float4),
+            ],
+            shader,
+            PipelineParams {
+                depth_test: Comparison::Always,
+                depth_write: false,
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+        );
+
+        Self {
+            pipeline,
+            bindings,
+            vertices: Vec::new(),
+            scale: 2.0 * context.dpi_scale(),
+        }
+    }
+
+    /// Height in screen pixels of a line of text, including spacing
+    pub fn line_height(&self) -> f32 {
+        (CELL_HEIGHT + 2) as f32 * self.scale
+    }
+
+    /// Width in screen pixels of a single line of text
+    pub fn width(&self, text: &str) -> f32 {
+        font::text_width(text) as f32 * self.scale
+    }
+
+    /// Queues a single line of text with its top left corner at (x, y), in
+    /// screen pixels.
+    pub fn queue(&mut self, text: &str, x: f32, y: f32, color: [f32; 4]) {
+        let atlas_width = (CELL_WIDTH * NUM_CHARS) as f32;
+        let (w, h) = (
+            CELL_WIDTH as f32 * self.scale,
+            CELL_HEIGHT as f32 * self.scale,
+        );
+        for (i, c) in text.chars().enumerate() {
+            if self.vertices.len() / 4 >= MAX_GLYPHS {
+                return;
+            }
+            let index = match c {
+                ' '..='~' => c as usize - FIRST_CHAR as usize,
+                _ => '?' as usize - FIRST_CHAR as usize,
+            };
+            let (u0, u1) = (
+                (index * CELL_WIDTH) as f32 / atlas_width,
+                ((index + 1) * CELL_WIDTH) as f32 / atlas_width,
+            );
+            let x0 = x + (i * ADVANCE) as f32 * self.scale;
+            for (pos, tex_coord) in [
+                ([x0, y], [u0, 0.0]),
+                ([x0 + w, y], [u1, 0.0]),
+                ([x0 + w, y + h], [u1, 1.0]),
+                ([x0, y + h], [u0, 1.0]),
+            ] {
+                self.vertices.push(TextVertex {
+                    pos,
+                    tex_coord,
+                    color,
+                });
+            }
+        }
+    }
+
+    /// Draws and clears all queued text. Must be called within a render pass.
+    pub fn draw(&mut self, context: &mut Context) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let (width, height) = context.screen_size();
+        self.bindings.vertex_buffers[0].update(context, &self.vertices);
+        context.apply_pipeline(&self.pipeline);
+        context.apply_bindings(&self.bindings);
+        context.apply_uniforms(&TextUniforms {
+            screen_size: [width, height],
+        });
+        context.draw(0, (self.vertices.len() / 4 * 6) as i32, 1);
+        self.vertices.clear();
+    }
+}
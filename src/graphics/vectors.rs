@@ -0,0 +1,133 @@
+// Arrows showing the forces on each body and its velocity, for debugging
+// the dynamics visually.
+use glam::{Mat4, Vec2, Vec3};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use super::hud::to_screen;
+use super::lines::LineRenderer;
+use super::scale::SceneScale;
+use super::text::TextRenderer;
+use super::{to_vec3, BodyStateMap};
+use crate::math::{Distance, Vector3};
+
+/// Scene length of an arrow per order of magnitude above the smallest
+/// vector of its kind
+const ARROW_LENGTH: f32 = 0.01;
+const NET_FORCE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+const VELOCITY_COLOR: [f32; 4] = [0.2, 1.0, 0.4, 0.9];
+
+/// Scene length of an arrow for a vector of the given magnitude, growing
+/// with the log of its ratio to the reference so that vectors many orders
+/// of magnitude apart are all visible
+pub fn arrow_length(magnitude: f32, reference: f32) -> f32 {
+    if reference > 0.0 {
+        ARROW_LENGTH * (1.0 + magnitude / reference).log10()
+    } else {
+        0.0
+    }
+}
+
+/// Colour of an individual force, picked from its label so that the same
+/// force keeps its colour from frame to frame
+fn force_color(label: &str) -> [f32; 4] {
+    let mut hasher = DefaultHasher::new();
+    label.hash(&mut hasher);
+    let hue = (hasher.finish() % 360) as f32 / 60.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let [r, g, b] = match hue as u32 {
+        0 => [1.0, x, 0.0],
+        1 => [x, 1.0, 0.0],
+        2 => [0.0, 1.0, x],
+        3 => [0.0, x, 1.0],
+        4 => [x, 0.0, 1.0],
+        _ => [1.0, 0.0, x],
+    };
+    [r, g, b, 0.9]
+}
+
+/// Smallest non-zero magnitude of the vectors, used as the reference for
+/// their arrow lengths
+fn smallest(magnitudes: impl Iterator<Item = f32>) -> f32 {
+    magnitudes
+        .filter(|m| *m > 0.0)
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Which vectors are drawn on the bodies
+#[derive(Debug, Default)]
+pub struct VectorOverlay {
+    pub net_force: bool,
+    pub forces: bool,
+    pub velocity: bool,
+}
+
+impl VectorOverlay {
+    /// Queues arrows from the centre of each body for the vectors shown,
+    /// with individual forces labelled at their tips.
+    pub fn queue(
+        &self,
+        lines: &mut LineRenderer,
+        text: &mut TextRenderer,
+        body_state_map: &BodyStateMap,
+        scale: &SceneScale,
+        view_projection: Mat4,
+        screen_size: Vec2,
+    ) {
+        let net_forces: Vec<Vector3> = body_state_map
+            .values()
+            .map(|b| b.forces.iter().map(|f| f.v).sum())
+            .collect();
+        // Net and individual forces share a reference, so they're comparable
+        let force_reference = smallest(
+            body_state_map
+                .values()
+                .flat_map(|b| b.forces.iter().map(|f| f.magnitude()))
+                .chain(net_forces.iter().map(|f| f.magnitude())),
+        );
+        let velocity_reference = smallest(body_state_map.values().map(|b| b.velocity.magnitude()));
+
+        let arrow = |v: &Vector3, reference: f32| {
+            let magnitude = v.magnitude();
+            if magnitude > 0.0 {
+                to_vec3(v) / magnitude * arrow_length(magnitude, reference)
+            } else {
+                Vec3::ZERO
+            }
+        };
+        for (body_state, net_force) in body_state_map.values().zip(&net_forces) {
+            let center = scale.position(&body_state.pos);
+            if self.net_force {
+                let tip = center + arrow(net_force, force_reference);
+                lines.queue_arrow(center, tip, NET_FORCE_COLOR);
+            }
+            if self.forces {
+                for force in &body_state.forces {
+                    let color = force_color(&force.label);
+                    let tip = center + arrow(&force.v, force_reference);
+                    lines.queue_arrow(center, tip, color);
+                    if let Some(screen) = to_screen(tip, view_projection, screen_size) {
+                        text.queue(&force.label, screen.x, screen.y, color);
+                    }
+                }
+            }
+            if self.velocity {
+                let tip = center + arrow(&body_state.velocity, velocity_reference);
+                lines.queue_arrow(center, tip, VELOCITY_COLOR);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrow_lengths_grow_with_orders_of_magnitude() {
+        assert_eq!(arrow_length(0.0, 1.0), 0.0);
+        assert_eq!(arrow_length(9.0, 1.0), ARROW_LENGTH);
+        assert_eq!(arrow_length(99.0, 1.0), 2.0 * ARROW_LENGTH);
+        assert_eq!(arrow_length(1.0, 0.0), 0.0);
+    }
+}
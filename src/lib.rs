@@ -1,6 +1,9 @@
 pub mod config;
+pub mod connection;
 pub mod force;
 pub mod graphics;
+pub mod maneuver;
 pub mod math;
 pub mod output_adapter;
 pub mod simulation;
+pub mod trajectory;
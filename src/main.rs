@@ -1,10 +1,13 @@
 use clap::{Parser, ValueEnum};
 use miniquad;
 use simulator::config::Config;
-use simulator::graphics::{self, Stage};
+use simulator::graphics::{self, headless::HeadlessRenderer, Stage};
 use simulator::output_adapter::{
-    csv_adapter::CsvAdapter, stdout_adapter::StdoutAdapter, OutputAdapter,
+    csv_adapter::CsvAdapter, plot_adapter::PlotAdapter, stdout_adapter::StdoutAdapter,
+    trajectory_adapter::TrajectoryAdapter, vtk_adapter::VtkAdapter, OutputAdapter,
+    DEFAULT_OUTPUT_DIR,
 };
+use simulator::trajectory::Trajectory;
 use std::path::PathBuf;
 use std::{error::Error, fs};
 
@@ -16,6 +19,14 @@ enum OutputType {
     Csv,
     /// Render the simulation graphically in a window
     Graphical,
+    /// VTK point files for each step, indexed by a ParaView .pvd collection
+    Vtk,
+    /// SVG and PNG plots of the projected trajectories of all bodies
+    Plot,
+    /// Render the simulation graphically to a PNG sequence, without a window
+    Render,
+    /// Binary trajectory file that can be replayed in the graphical output
+    Trajectory,
 }
 
 #[derive(Parser, Debug)]
@@ -28,6 +39,15 @@ struct Args {
     /// Format of the simulation's output
     #[arg(short, long, value_enum, default_value_t = OutputType::Csv)]
     output: OutputType,
+
+    /// Directory to write file-based output to
+    #[arg(short = 'd', long, default_value = DEFAULT_OUTPUT_DIR)]
+    output_dir: PathBuf,
+
+    /// Recorded trajectory (CSV or binary) to play back in the graphical
+    /// output instead of running the simulation
+    #[arg(short, long)]
+    replay: Option<PathBuf>,
 }
 
 fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
@@ -39,17 +59,45 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     match args.output {
         OutputType::Stdout => {
             let adapter = StdoutAdapter::new(&sim);
-            adapter.output();
+            adapter.output()?;
         }
         OutputType::Csv => {
             let adapter = CsvAdapter::new(&sim);
-            adapter.output();
+            adapter.output()?;
+        }
+        OutputType::Vtk => {
+            let adapter = VtkAdapter::new(&sim).with_output_dir(args.output_dir);
+            adapter.output()?;
+        }
+        OutputType::Plot => {
+            let adapter = PlotAdapter::new(&sim)
+                .with_config(config.plot)
+                .with_output_dir(args.output_dir);
+            adapter.output()?;
+        }
+        OutputType::Trajectory => {
+            let adapter = TrajectoryAdapter::new(&sim).with_output_dir(args.output_dir);
+            adapter.output()?;
+        }
+        OutputType::Render => {
+            let config_root = args.infile.parent().unwrap().to_path_buf();
+            let mut renderer =
+                HeadlessRenderer::new(sim, &config.models, &config_root, config.render);
+            renderer.render(&args.output_dir)?;
         }
         OutputType::Graphical => {
             let graphics_conf = graphics::new_conf();
             let config_root = args.infile.parent().unwrap().to_path_buf();
+            let trajectory = match &args.replay {
+                Some(path) => Some(Trajectory::load(path)?),
+                None => None,
+            };
             miniquad::start(graphics_conf, move |ctx| {
-                Box::new(Stage::new(ctx, sim, config.models, config_root))
+                let stage = Stage::new(ctx, sim, config.models, config_root, config.viewer);
+                match trajectory {
+                    Some(trajectory) => Box::new(stage.with_replay(trajectory)),
+                    None => Box::new(stage),
+                }
             });
         }
     }
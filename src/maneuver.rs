@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::math::{Distance, Vector3};
+use crate::simulation::Body;
+
+/// Standard gravity in metres per second squared, relating specific
+/// impulse to exhaust velocity
+pub const STANDARD_GRAVITY: f32 = 9.80665;
+
+/// A planned change to a spacecraft's velocity. Directions are in the
+/// simulation's axes, or when `relative_to` names a reference body, as
+/// prograde, normal and radial components relative to the orbit about it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Maneuver {
+    /// Instant change of velocity `delta_v` at time `t`. With a specific
+    /// impulse `isp` in seconds, the fuel it takes is spent from the
+    /// body's mass.
+    Impulse {
+        t: f32,
+        delta_v: Vector3,
+        #[serde(default)]
+        relative_to: Option<String>,
+        #[serde(default)]
+        isp: Option<f32>,
+    },
+    /// Constant `thrust` in newtons along `direction` for `duration`
+    /// seconds from time `t`, burning fuel at a rate set by the specific
+    /// impulse `isp` in seconds until the body is down to its dry mass.
+    Burn {
+        t: f32,
+        duration: f32,
+        thrust: f32,
+        isp: f32,
+        direction: Vector3,
+        #[serde(default)]
+        relative_to: Option<String>,
+    },
+}
+
+impl Maneuver {
+    /// Time the maneuver starts
+    pub fn t(&self) -> f32 {
+        match self {
+            Self::Impulse { t, .. } | Self::Burn { t, .. } => *t,
+        }
+    }
+
+    pub fn relative_to(&self) -> Option<&str> {
+        match self {
+            Self::Impulse { relative_to, .. } | Self::Burn { relative_to, .. } => {
+                relative_to.as_deref()
+            }
+        }
+    }
+
+    /// Whether the maneuver spends fuel, so the body needs a dry mass
+    pub fn burns_fuel(&self) -> bool {
+        match self {
+            Self::Impulse { isp, .. } => isp.is_some(),
+            Self::Burn { .. } => true,
+        }
+    }
+
+    /// Whether the maneuver starts during the step from `t` over `t_step`,
+    /// which is negative when running in reverse
+    pub fn starts_within(&self, t: f32, t_step: f32) -> bool {
+        let (from, to) = if t_step < 0.0 {
+            (t + t_step, t)
+        } else {
+            (t, t + t_step)
+        };
+        (from..to).contains(&self.t())
+    }
+
+    /// Whether a finite burn is firing during the step from `t`
+    pub fn is_burning(&self, t: f32, t_step: f32) -> bool {
+        match self {
+            Self::Burn {
+                t: start, duration, ..
+            } => (*start..start + duration).contains(&(t + t_step / 2.0)),
+            Self::Impulse { .. } => false,
+        }
+    }
+}
+
+/// What a body's maneuvers did once its fuel ran short, so that running
+/// in reverse undoes no more than that
+#[derive(Debug, Clone, Default)]
+pub struct FuelRecord {
+    /// Time the fuel ran out, after which nothing fires
+    pub burnout: Option<f32>,
+    /// Fraction of the delta-v applied by impulses that were cut short, by
+    /// their index in the body's maneuvers
+    pub cut_impulses: BTreeMap<usize, f32>,
+}
+
+/// Prograde, normal and radially outward unit vectors of the orbit of
+/// `body` about `reference`
+pub fn orbital_axes<const N: usize>(body: &Body<N>, reference: &Body<N>) -> [Vector3; 3] {
+    let r = (&body.position - &reference.position).resized::<3>();
+    let v = (&body.velocity - &reference.velocity).resized::<3>();
+    let prograde = v.normalize();
+    let normal = r.cross(&v).normalize();
+    let radial = prograde.cross(&normal);
+    [prograde, normal, radial]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{OwningRun, Run, Simulation};
+    use approx::assert_abs_diff_eq;
+
+    fn spacecraft(maneuvers: Vec<Maneuver>) -> Body<3> {
+        let mut body = Body::new(
+            String::from("spacecraft"),
+            1000.0,
+            2.0,
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Default::default(),
+        );
+        body.dry_mass = Some(400.0);
+        body.maneuvers = maneuvers;
+        body
+    }
+
+    #[test]
+    fn orbital_axes_follow_the_orbit() {
+        let reference = Body::new(
+            String::from("planet"),
+            1.0,
+            1.0,
+            Vector3::default(),
+            Vector3::default(),
+            Default::default(),
+        );
+        let [prograde, normal, radial] = orbital_axes(&spacecraft(vec![]), &reference);
+        assert_eq!(prograde, Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(normal, Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(radial, Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn impulses_change_velocity_once_and_spend_fuel() {
+        let mut simulation = Simulation::new(Some(0.0), Some(2.0), Some(0.1));
+        simulation.add_body(spacecraft(vec![Maneuver::Impulse {
+            t: 1.0,
+            delta_v: Vector3::new(0.0, 0.0, 2.0),
+            relative_to: None,
+            isp: Some(10.0 / STANDARD_GRAVITY),
+        }]));
+        let body_map = Run::from(&simulation).last().unwrap().body_map;
+        let spacecraft = &body_map["spacecraft"];
+        assert_eq!(spacecraft.velocity, Vector3::new(0.0, 0.0, 1.0));
+        assert_abs_diff_eq!(spacecraft.mass, 1000.0 * (-0.2_f32).exp(), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn finite_burns_follow_the_rocket_equation() {
+        let (thrust, isp) = (100.0, 10.0);
+        let mut simulation = Simulation::new(Some(0.0), Some(60.0), Some(0.01));
+        simulation.add_body(spacecraft(vec![Maneuver::Burn {
+            t: 0.0,
+            duration: 30.0,
+            thrust,
+            isp,
+            direction: Vector3::new(0.0, 0.0, 1.0),
+            relative_to: None,
+        }]));
+        let body_map = Run::from(&simulation).last().unwrap().body_map;
+        let spacecraft = &body_map["spacecraft"];
+        // 30 s at 100 N / (10 s × g₀) burns 30.6 kg of fuel
+        let mass = 1000.0 - 30.0 * thrust / (isp * STANDARD_GRAVITY);
+        assert_abs_diff_eq!(spacecraft.mass, mass, epsilon = 0.1);
+        let delta_v = isp * STANDARD_GRAVITY * (1000.0 / mass).ln();
+        assert_abs_diff_eq!(spacecraft.velocity.z(), -1.0 + delta_v, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn burns_stop_when_the_fuel_runs_out() {
+        let (thrust, isp) = (1000.0, 10.0);
+        let mut simulation = Simulation::new(Some(0.0), Some(100.0), Some(0.01));
+        simulation.add_body(spacecraft(vec![Maneuver::Burn {
+            t: 0.0,
+            duration: 100.0,
+            thrust,
+            isp,
+            direction: Vector3::new(0.0, 0.0, 1.0),
+            relative_to: None,
+        }]));
+        let body_map = Run::from(&simulation).last().unwrap().body_map;
+        let spacecraft = &body_map["spacecraft"];
+        // All 600 kg of fuel is burnt in under 59 s
+        assert_abs_diff_eq!(spacecraft.mass, 400.0, epsilon = 1e-3);
+        let delta_v = isp * STANDARD_GRAVITY * (1000.0_f32 / 400.0).ln();
+        assert_abs_diff_eq!(spacecraft.velocity.z(), -1.0 + delta_v, epsilon = 0.02);
+    }
+
+    #[test]
+    fn impulses_are_cut_short_by_running_out_of_fuel() {
+        let mut simulation = Simulation::new(Some(0.0), Some(2.0), Some(0.1));
+        simulation.add_body(spacecraft(vec![Maneuver::Impulse {
+            t: 1.0,
+            delta_v: Vector3::new(0.0, 0.0, 100.0),
+            relative_to: None,
+            isp: Some(10.0 / STANDARD_GRAVITY),
+        }]));
+        let body_map = Run::from(&simulation).last().unwrap().body_map;
+        let spacecraft = &body_map["spacecraft"];
+        assert_eq!(spacecraft.mass, 400.0);
+        let delta_v = 10.0 * (1000.0_f32 / 400.0).ln();
+        assert_abs_diff_eq!(spacecraft.velocity.z(), -1.0 + delta_v, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn bodies_burning_fuel_need_a_dry_mass() {
+        let yaml = "t_start: 0.0
+t_step: 1.0
+bodies:
+  - label: spacecraft
+    mass: 1000.0
+    diameter: 2.0
+    position: [0.0, 0.0, 0.0]
+    velocity: [0.0, 0.0, 0.0]
+    maneuvers:
+      - burn: {t: 0.0, duration: 1.0, thrust: 1.0, isp: 300.0, direction: [1.0, 0.0, 0.0]}
+";
+        let error = serde_yaml::from_str::<Simulation<3>>(yaml).unwrap_err();
+        assert!(error.to_string().contains("no dry_mass"));
+        let yaml = format!("{}    dry_mass: 400.0\n", yaml);
+        assert!(serde_yaml::from_str::<Simulation<3>>(&yaml).is_ok());
+    }
+
+    /// Runs the simulation to its end and back again, returning the body's
+    /// final mass and velocity along z
+    fn run_there_and_back(simulation: Simulation<3>) -> (f32, f32) {
+        let mut run = OwningRun::from(simulation);
+        run.by_ref().last();
+        run.reverse();
+        let body_map = run.last().unwrap().body_map;
+        let spacecraft = &body_map["spacecraft"];
+        (spacecraft.mass, spacecraft.velocity.z())
+    }
+
+    #[test]
+    fn reversing_burns_undoes_only_the_thrust_that_fired() {
+        let mut simulation = Simulation::new(Some(0.0), Some(100.0), Some(0.01));
+        simulation.add_body(spacecraft(vec![Maneuver::Burn {
+            t: 0.0,
+            duration: 100.0,
+            thrust: 1000.0,
+            isp: 10.0,
+            direction: Vector3::new(0.0, 0.0, 1.0),
+            relative_to: None,
+        }]));
+        let (mass, velocity) = run_there_and_back(simulation);
+        assert_abs_diff_eq!(mass, 1000.0, epsilon = 1e-2);
+        assert_abs_diff_eq!(velocity, -1.0, epsilon = 0.05);
+    }
+
+    #[test]
+    fn reversing_cut_impulses_undoes_only_the_delta_v_applied() {
+        let mut simulation = Simulation::new(Some(0.0), Some(2.0), Some(0.1));
+        simulation.add_body(spacecraft(vec![Maneuver::Impulse {
+            t: 1.0,
+            delta_v: Vector3::new(0.0, 0.0, 100.0),
+            relative_to: None,
+            isp: Some(10.0 / STANDARD_GRAVITY),
+        }]));
+        let (mass, velocity) = run_there_and_back(simulation);
+        assert_abs_diff_eq!(mass, 1000.0, epsilon = 1e-2);
+        assert_abs_diff_eq!(velocity, -1.0, epsilon = 1e-4);
+    }
+}
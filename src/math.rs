@@ -1,4 +1,6 @@
 pub mod matrix;
+pub mod quaternion;
 pub mod vector;
 pub use matrix::*;
+pub use quaternion::*;
 pub use vector::*;
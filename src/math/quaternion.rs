@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Mul;
+
+use super::vector::{Distance, Vector3};
+
+/// A rotation in three dimensions, as a unit quaternion w + xi + yj + zk.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quaternion {
+    pub const IDENTITY: Self = Self::new(1.0, 0.0, 0.0, 0.0);
+
+    pub const fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// Rotation by `angle` radians about `axis`, which needn't be
+    /// normalized. A zero axis gives no rotation.
+    pub fn from_axis_angle(axis: &Vector3, angle: f32) -> Self {
+        let length = axis.magnitude();
+        if length == 0.0 {
+            return Self::IDENTITY;
+        }
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let s = sin / length;
+        Self::new(cos, s * axis.x(), s * axis.y(), s * axis.z())
+    }
+
+    /// Rotation by the length of `rotation` in radians about its direction
+    pub fn from_rotation_vector(rotation: &Vector3) -> Self {
+        Self::from_axis_angle(rotation, rotation.magnitude())
+    }
+
+    /// The inverse rotation, for unit quaternions
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn dot(&self, rhs: &Self) -> f32 {
+        self.w * rhs.w + self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    pub fn magnitude(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Scales the quaternion back to unit length, correcting the drift from
+    /// repeatedly composing rotations
+    pub fn normalize(&self) -> Self {
+        let m = self.magnitude();
+        Self::new(self.w / m, self.x / m, self.y / m, self.z / m)
+    }
+
+    /// Rotates a vector
+    pub fn rotate(&self, v: &Vector3) -> Vector3 {
+        // v' = v + 2w(u × v) + 2u × (u × v), with u the vector part
+        let u = Vector3::new(self.x, self.y, self.z);
+        let uv = u.cross(v);
+        let uuv = u.cross(&uv);
+        &(v + &((2.0 * self.w) * &uv)) + &(2.0 * &uuv)
+    }
+
+    /// Angle in radians and unit axis of the rotation. The axis is
+    /// arbitrary for the identity.
+    pub fn to_axis_angle(&self) -> (Vector3, f32) {
+        let q = if self.w < 0.0 {
+            Self::new(-self.w, -self.x, -self.y, -self.z)
+        } else {
+            *self
+        };
+        let sin = (q.x * q.x + q.y * q.y + q.z * q.z).sqrt();
+        if sin == 0.0 {
+            return (Vector3::new(0.0, 1.0, 0.0), 0.0);
+        }
+        let axis = Vector3::new(q.x / sin, q.y / sin, q.z / sin);
+        (axis, 2.0 * sin.atan2(q.w))
+    }
+
+    /// Spherical linear interpolation from this rotation to another, taking
+    /// the shorter way round
+    pub fn slerp(&self, to: &Self, t: f32) -> Self {
+        let mut cos = self.dot(to);
+        let to = if cos < 0.0 {
+            cos = -cos;
+            Self::new(-to.w, -to.x, -to.y, -to.z)
+        } else {
+            *to
+        };
+        // Nearly identical rotations, where slerp is ill-conditioned, are
+        // interpolated linearly
+        let (a, b) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Self::new(
+            a * self.w + b * to.w,
+            a * self.x + b * to.x,
+            a * self.y + b * to.y,
+            a * self.z + b * to.z,
+        )
+        .normalize()
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Self;
+
+    /// Composes two rotations, applying `rhs` first
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use std::f32::consts::PI;
+
+    fn assert_vectors_eq(a: Vector3, b: Vector3) {
+        for i in 0..3 {
+            assert_abs_diff_eq!(a[i], b[i], epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn rotating_a_vector_about_an_axis_works() {
+        let q = Quaternion::from_axis_angle(&Vector3::new(0.0, 0.0, 2.0), PI / 2.0);
+        let rotated = q.rotate(&Vector3::new(1.0, 0.0, 0.0));
+        assert_vectors_eq(rotated, Vector3::new(0.0, 1.0, 0.0));
+        assert_vectors_eq(q.conjugate().rotate(&rotated), Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn multiplying_quaternions_composes_rotations() {
+        let about_z = Quaternion::from_axis_angle(&Vector3::new(0.0, 0.0, 1.0), PI / 2.0);
+        let about_x = Quaternion::from_axis_angle(&Vector3::new(1.0, 0.0, 0.0), PI / 2.0);
+        let v = Vector3::new(1.0, 0.0, 0.0);
+        // z first takes x to y, then x takes y to z
+        assert_vectors_eq((about_x * about_z).rotate(&v), Vector3::new(0.0, 0.0, 1.0));
+        assert_vectors_eq(
+            (about_x * about_z).rotate(&v),
+            about_x.rotate(&about_z.rotate(&v)),
+        );
+    }
+
+    #[test]
+    fn converting_to_axis_and_angle_round_trips() {
+        let axis = Vector3::new(1.0, 2.0, 2.0).normalize();
+        let (to_axis, angle) = Quaternion::from_axis_angle(&axis, 1.2).to_axis_angle();
+        assert_vectors_eq(to_axis, axis);
+        assert_abs_diff_eq!(angle, 1.2, epsilon = 1e-6);
+        assert_eq!(Quaternion::IDENTITY.to_axis_angle().1, 0.0);
+    }
+
+    #[test]
+    fn slerp_interpolates_the_angle() {
+        let axis = Vector3::new(0.0, 1.0, 0.0);
+        let from = Quaternion::from_axis_angle(&axis, 0.2);
+        let to = Quaternion::from_axis_angle(&axis, 1.0);
+        let (_, angle) = from.slerp(&to, 0.25).to_axis_angle();
+        assert_abs_diff_eq!(angle, 0.4, epsilon = 1e-5);
+        assert_eq!(from.slerp(&to, 0.0), from);
+    }
+}
@@ -198,6 +198,16 @@ impl<const N: usize> Vector<N> {
         }
         sum
     }
+
+    /// Converts the vector to one with M components, truncating the
+    /// trailing components or padding with zeros as needed.
+    pub fn resized<const M: usize>(&self) -> Vector<M> {
+        let mut components = [0.0; M];
+        for (c, v) in components.iter_mut().zip(self.0.iter()) {
+            *c = *v;
+        }
+        Vector::<M>(components)
+    }
 }
 
 #[cfg(test)]
@@ -348,4 +358,14 @@ mod tests {
         let v3 = Vector3::new(-0.5, -0.5, -0.5);
         assert_eq!(Vector3::normal(&v1, &v2, &v3), Vector3::new(0.0, 0.0, -1.0));
     }
+
+    #[test]
+    fn resizing_a_vector_pads_and_truncates() {
+        let v = Vector2::new(1.0, 2.0);
+        assert_eq!(v.resized::<3>(), Vector3::new(1.0, 2.0, 0.0));
+        assert_eq!(
+            Vector3::new(1.0, 2.0, 3.0).resized::<1>(),
+            Vector1::new(1.0)
+        );
+    }
 }
@@ -1,9 +1,18 @@
 use crate::simulation::Simulation;
+use std::error::Error;
+
+pub type OutputResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+/// Directory file-based output is written to unless another is given
+pub const DEFAULT_OUTPUT_DIR: &str = "output";
 
 pub trait OutputAdapter<'a, const N: usize> {
     fn new(simulation: &'a Simulation<N>) -> Self;
-    fn output(&'a self);
+    fn output(&'a self) -> OutputResult;
 }
 
 pub mod csv_adapter;
+pub mod plot_adapter;
 pub mod stdout_adapter;
+pub mod trajectory_adapter;
+pub mod vtk_adapter;
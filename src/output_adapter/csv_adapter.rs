@@ -1,4 +1,4 @@
-use crate::output_adapter::OutputAdapter;
+use crate::output_adapter::{OutputAdapter, OutputResult};
 use crate::simulation::{Body, BodyMap, Run, Simulation};
 
 pub struct CsvAdapter<'a, const N: usize> {
@@ -10,7 +10,7 @@ impl<'a, const N: usize> OutputAdapter<'a, N> for CsvAdapter<'a, N> {
         Self { simulation }
     }
 
-    fn output(&self) {
+    fn output(&self) -> OutputResult {
         println!("{}", self.headers());
         let run = Run::from(self.simulation);
         let order = self
@@ -22,6 +22,7 @@ impl<'a, const N: usize> OutputAdapter<'a, N> for CsvAdapter<'a, N> {
         for step in run {
             println!("{}", self.body_row(step.t, &step.body_map, &order));
         }
+        Ok(())
     }
 }
 
@@ -54,7 +55,7 @@ impl<'a, const N: usize> CsvAdapter<'a, N> {
     }
 
     fn body_row(&self, t: f32, body_states: &'a BodyMap<N>, order: &Vec<String>) -> String {
-        let mut row = format!("{:.1}", t);
+        let mut row = format!("{}", t);
         for label in order {
             if let Some(body) = body_states.get(label) {
                 row.push_str(&format!("{}", &Self::body_data(body)));
@@ -0,0 +1,567 @@
+use crate::graphics::font;
+use crate::math::{Distance, Vector2, Vector3};
+use crate::output_adapter::{OutputAdapter, OutputResult, DEFAULT_OUTPUT_DIR};
+use crate::simulation::{Run, Simulation};
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+const PALETTE: [[u8; 3]; 10] = [
+    [0x1f, 0x77, 0xb4],
+    [0xff, 0x7f, 0x0e],
+    [0x2c, 0xa0, 0x2c],
+    [0xd6, 0x27, 0x28],
+    [0x94, 0x67, 0xbd],
+    [0x8c, 0x56, 0x4b],
+    [0xe3, 0x77, 0xc2],
+    [0x7f, 0x7f, 0x7f],
+    [0xbc, 0xbd, 0x22],
+    [0x17, 0xbe, 0xcf],
+];
+const BACKGROUND: [u8; 3] = [0xff, 0xff, 0xff];
+const FOREGROUND: [u8; 3] = [0x20, 0x20, 0x20];
+const MARGIN: f32 = 40.0;
+/// Smallest width or height in pixels leaving room for a plot within the
+/// margins
+const MIN_SIZE: u32 = 100;
+const TEXT_SCALE: usize = 2;
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Plane {
+    #[default]
+    Xy,
+    Xz,
+    Yz,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlotConfig {
+    /// Plane the trajectories are projected onto
+    pub plane: Plane,
+    /// Direction the plot is viewed along, which overrides `plane`
+    pub view_direction: Option<Vector3>,
+    /// Direction that appears upwards when viewing along `view_direction`
+    pub view_up: Option<Vector3>,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub width: u32,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub height: u32,
+    /// Colours of each body's trajectory by label, as `#rrggbb`
+    #[serde(deserialize_with = "deserialize_colors")]
+    pub colors: HashMap<String, String>,
+}
+
+impl Default for PlotConfig {
+    fn default() -> Self {
+        Self {
+            plane: Plane::Xy,
+            view_direction: None,
+            view_up: None,
+            width: 1024,
+            height: 1024,
+            colors: HashMap::new(),
+        }
+    }
+}
+
+impl PlotConfig {
+    /// Returns the orthonormal (right, up) basis of the projection plane
+    fn basis(&self) -> (Vector3, Vector3) {
+        let x = Vector3::new(1.0, 0.0, 0.0);
+        let y = Vector3::new(0.0, 1.0, 0.0);
+        let z = Vector3::new(0.0, 0.0, 1.0);
+        match self.view_direction {
+            Some(direction) => {
+                let direction = direction.normalize();
+                let mut up = self.view_up.unwrap_or(z);
+                if direction.cross(&up).magnitude() < 1e-6 {
+                    up = y;
+                }
+                let right = direction.cross(&up).normalize();
+                (right, right.cross(&direction))
+            }
+            None => match self.plane {
+                Plane::Xy => (x, y),
+                Plane::Xz => (x, z),
+                Plane::Yz => (y, z),
+            },
+        }
+    }
+
+    fn axis_labels(&self) -> (&'static str, &'static str) {
+        match (self.view_direction, self.plane) {
+            (Some(_), _) => ("right", "up"),
+            (None, Plane::Xy) => ("x", "y"),
+            (None, Plane::Xz) => ("x", "z"),
+            (None, Plane::Yz) => ("y", "z"),
+        }
+    }
+}
+
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let size = u32::deserialize(deserializer)?;
+    if size < MIN_SIZE {
+        return Err(serde::de::Error::custom(format!(
+            "plot width and height must be at least {} pixels",
+            MIN_SIZE
+        )));
+    }
+    Ok(size)
+}
+
+fn deserialize_colors<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, String>, D::Error> {
+    let colors = HashMap::<String, String>::deserialize(deserializer)?;
+    for (label, color) in &colors {
+        if parse_color(color).is_none() {
+            return Err(serde::de::Error::custom(format!(
+                "plot color {} of {} must be formatted as #rrggbb",
+                color, label
+            )));
+        }
+    }
+    Ok(colors)
+}
+
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+struct Trace {
+    label: String,
+    color: [u8; 3],
+    points: Vec<Vector2>,
+}
+
+/// Renders the projected trajectories of every body to an SVG and a PNG,
+/// entirely in software.
+pub struct PlotAdapter<'a, const N: usize> {
+    simulation: &'a Simulation<N>,
+    config: PlotConfig,
+    output_dir: PathBuf,
+}
+
+impl<'a, const N: usize> OutputAdapter<'a, N> for PlotAdapter<'a, N> {
+    fn new(simulation: &'a Simulation<N>) -> Self {
+        Self {
+            simulation,
+            config: PlotConfig::default(),
+            output_dir: PathBuf::from(DEFAULT_OUTPUT_DIR),
+        }
+    }
+
+    fn output(&self) -> OutputResult {
+        if self.simulation.t_end().is_none() {
+            return Err("plot output requires the simulation to have a t_end".into());
+        }
+        fs::create_dir_all(&self.output_dir)?;
+
+        let traces = self.project_traces();
+        let transform = self.fit(&traces);
+        let traces: Vec<Trace> = traces
+            .into_iter()
+            .map(|trace| Trace {
+                points: thin(trace.points.iter().map(&transform)),
+                ..trace
+            })
+            .collect();
+
+        fs::write(self.output_dir.join("trajectories.svg"), self.svg(&traces))?;
+        self.raster(&traces)
+            .save(self.output_dir.join("trajectories.png"))?;
+        Ok(())
+    }
+}
+
+impl<'a, const N: usize> PlotAdapter<'a, N> {
+    pub fn with_config(mut self, config: PlotConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_output_dir(mut self, output_dir: PathBuf) -> Self {
+        self.output_dir = output_dir;
+        self
+    }
+
+    /// Runs the simulation, projecting each body's positions onto the plane
+    fn project_traces(&self) -> Vec<Trace> {
+        let (right, up) = self.config.basis();
+        let mut traces: Vec<Trace> = self
+            .simulation
+            .bodies()
+            .iter()
+            .enumerate()
+            .map(|(i, b)| Trace {
+                label: b.label.clone(),
+                color: self
+                    .config
+                    .colors
+                    .get(&b.label)
+                    .and_then(|c| parse_color(c))
+                    .unwrap_or(PALETTE[i % PALETTE.len()]),
+                points: Vec::new(),
+            })
+            .collect();
+
+        for step in Run::from(self.simulation) {
+            for trace in &mut traces {
+                if let Some(body) = step.body_map.get(&trace.label) {
+                    let p = body.position.resized::<3>();
+                    trace.points.push(Vector2::new(p.dot(&right), p.dot(&up)));
+                }
+            }
+        }
+        traces
+    }
+
+    /// Returns the transform from projected coordinates to image pixels that
+    /// fits all trajectories within the margins, preserving aspect ratio.
+    fn fit(&self, traces: &[Trace]) -> impl Fn(&Vector2) -> Vector2 {
+        let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+        let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for p in traces.iter().flat_map(|t| &t.points) {
+            min_x = min_x.min(p.x());
+            min_y = min_y.min(p.y());
+            max_x = max_x.max(p.x());
+            max_y = max_y.max(p.y());
+        }
+        if min_x > max_x {
+            (min_x, min_y, max_x, max_y) = (0.0, 0.0, 0.0, 0.0);
+        }
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        let span_x = (max_x - min_x).max(f32::MIN_POSITIVE);
+        let span_y = (max_y - min_y).max(f32::MIN_POSITIVE);
+        // Sizes are at least MIN_SIZE when read from a config, but one set
+        // in code mustn't give a negative scale and mirror the plot
+        let (inner_width, inner_height) = (
+            (width - 2.0 * MARGIN).max(1.0),
+            (height - 2.0 * MARGIN).max(1.0),
+        );
+        let scale = (inner_width / span_x).min(inner_height / span_y);
+        let (mid_x, mid_y) = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+        move |p: &Vector2| {
+            Vector2::new(
+                width / 2.0 + (p.x() - mid_x) * scale,
+                height / 2.0 - (p.y() - mid_y) * scale,
+            )
+        }
+    }
+
+    /// Legend rows as (top, trace), limited to those fitting above the
+    /// axis label
+    fn legend_entries<'t>(&self, traces: &'t [Trace]) -> impl Iterator<Item = (f32, &'t Trace)> {
+        let line_height = ((font::GLYPH_HEIGHT + 4) * TEXT_SCALE) as f32;
+        let max_y = self.config.height as f32 - MARGIN - line_height;
+        traces
+            .iter()
+            .enumerate()
+            .map(move |(i, t)| (MARGIN / 2.0 + i as f32 * line_height, t))
+            .take_while(move |(y, _)| *y < max_y)
+    }
+
+    fn svg(&self, traces: &[Trace]) -> String {
+        let (width, height) = (self.config.width, self.config.height);
+        let rgb = |c: [u8; 3]| format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2]);
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+            w = width,
+            h = height
+        );
+        let _ = writeln!(
+            svg,
+            "  <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            rgb(BACKGROUND)
+        );
+        for trace in traces {
+            let points: Vec<String> = trace
+                .points
+                .iter()
+                .map(|p| format!("{:.1},{:.1}", p.x(), p.y()))
+                .collect();
+            let _ = writeln!(
+                svg,
+                "  <polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" points=\"{}\"/>",
+                rgb(trace.color),
+                points.join(" ")
+            );
+            if let Some(end) = trace.points.last() {
+                let _ = writeln!(
+                    svg,
+                    "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"4\" fill=\"{}\"/>",
+                    end.x(),
+                    end.y(),
+                    rgb(trace.color)
+                );
+            }
+        }
+        let font_size = font::GLYPH_HEIGHT * TEXT_SCALE + 2;
+        for (y, trace) in self.legend_entries(traces) {
+            let _ = writeln!(
+                svg,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" fill=\"{}\"/>",
+                MARGIN / 2.0,
+                y,
+                rgb(trace.color),
+                s = font_size
+            );
+            let _ = writeln!(
+                svg,
+                "  <text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" fill=\"{}\">{}</text>",
+                MARGIN / 2.0 + 2.0 * font_size as f32,
+                y + font_size as f32 - 2.0,
+                font_size,
+                rgb(FOREGROUND),
+                escape(&trace.label)
+            );
+        }
+        let (x_label, y_label) = self.config.axis_labels();
+        let _ = writeln!(
+            svg,
+            "  <text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" fill=\"{}\">{} / {}</text>",
+            MARGIN / 2.0,
+            height as f32 - MARGIN / 2.0,
+            font_size,
+            rgb(FOREGROUND),
+            x_label,
+            y_label
+        );
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn raster(&self, traces: &[Trace]) -> RgbImage {
+        let mut img = RgbImage::from_pixel(self.config.width, self.config.height, Rgb(BACKGROUND));
+        for trace in traces {
+            for segment in trace.points.windows(2) {
+                draw_line(&mut img, &segment[0], &segment[1], trace.color);
+            }
+            if let Some(end) = trace.points.last() {
+                fill_square(
+                    &mut img,
+                    end.x() as i64 - 3,
+                    end.y() as i64 - 3,
+                    7,
+                    trace.color,
+                );
+            }
+        }
+
+        let swatch = font::GLYPH_HEIGHT * TEXT_SCALE;
+        let x = (MARGIN / 2.0) as i64;
+        for (y, trace) in self.legend_entries(traces) {
+            fill_square(&mut img, x, y as i64, swatch as i64, trace.color);
+            draw_text(&mut img, x + 2 * swatch as i64, y as i64, &trace.label);
+        }
+        let (x_label, y_label) = self.config.axis_labels();
+        let y = self.config.height as i64 - (MARGIN / 2.0) as i64 - swatch as i64;
+        draw_text(&mut img, x, y, &format!("{} / {}", x_label, y_label));
+        img
+    }
+}
+
+/// Drops points that are within a pixel of the previously kept point
+fn thin(points: impl Iterator<Item = Vector2>) -> Vec<Vector2> {
+    let mut thinned: Vec<Vector2> = Vec::new();
+    for p in points {
+        match thinned.last() {
+            Some(last) if last.distance(&p) < 1.0 => {}
+            _ => thinned.push(p),
+        }
+    }
+    thinned
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn put_pixel(img: &mut RgbImage, x: i64, y: i64, color: [u8; 3]) {
+    if x >= 0 && y >= 0 && x < img.width() as i64 && y < img.height() as i64 {
+        img.put_pixel(x as u32, y as u32, Rgb(color));
+    }
+}
+
+fn fill_square(img: &mut RgbImage, x: i64, y: i64, size: i64, color: [u8; 3]) {
+    for dy in 0..size {
+        for dx in 0..size {
+            put_pixel(img, x + dx, y + dy, color);
+        }
+    }
+}
+
+// Bresenham's line algorithm
+fn draw_line(img: &mut RgbImage, from: &Vector2, to: &Vector2, color: [u8; 3]) {
+    let (mut x0, mut y0) = (from.x().round() as i64, from.y().round() as i64);
+    let (x1, y1) = (to.x().round() as i64, to.y().round() as i64);
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+    let mut err = dx + dy;
+    loop {
+        put_pixel(img, x0, y0, color);
+        if x0 == x1 && y0 == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x0 += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y0 += sy;
+        }
+    }
+}
+
+fn draw_text(img: &mut RgbImage, x: i64, y: i64, text: &str) {
+    let scale = TEXT_SCALE as i64;
+    for (px, py) in font::pixels(text) {
+        fill_square(
+            img,
+            x + px as i64 * scale,
+            y + py as i64 * scale,
+            scale,
+            FOREGROUND,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Body;
+
+    fn trace(points: &[(f32, f32)]) -> Trace {
+        Trace {
+            label: String::from("Moon"),
+            color: PALETTE[0],
+            points: points.iter().map(|&(x, y)| Vector2::new(x, y)).collect(),
+        }
+    }
+
+    #[test]
+    fn views_along_a_direction_have_an_orthonormal_basis() {
+        let config = PlotConfig {
+            view_direction: Some(Vector3::new(0.0, -2.0, 0.0)),
+            view_up: Some(Vector3::new(0.0, 0.0, 1.0)),
+            ..Default::default()
+        };
+        let (right, up) = config.basis();
+        assert_eq!(right, Vector3::new(-1.0, 0.0, 0.0));
+        assert_eq!(up, Vector3::new(0.0, 0.0, 1.0));
+        let config = PlotConfig {
+            plane: Plane::Yz,
+            ..Default::default()
+        };
+        let (right, up) = config.basis();
+        assert_eq!(
+            (right, up),
+            (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn fitting_keeps_the_aspect_ratio_within_the_margins() {
+        let simulation = Simulation::<3>::new(None, None, None);
+        let config = PlotConfig {
+            width: 280,
+            height: 180,
+            ..Default::default()
+        };
+        let adapter = PlotAdapter::new(&simulation).with_config(config);
+        let transform = adapter.fit(&[trace(&[(-1.0, -1.0), (3.0, 1.0)])]);
+        // 200 pixels across 4 units, centred, with y pointing down
+        assert_eq!(
+            transform(&Vector2::new(-1.0, -1.0)),
+            Vector2::new(40.0, 140.0)
+        );
+        assert_eq!(
+            transform(&Vector2::new(3.0, 1.0)),
+            Vector2::new(240.0, 40.0)
+        );
+    }
+
+    #[test]
+    fn thinning_drops_points_within_a_pixel() {
+        let points = [(0.0, 0.0), (0.5, 0.5), (1.0, 0.5), (3.0, 0.0), (3.2, 0.0)];
+        let thinned = thin(trace(&points).points.into_iter());
+        assert_eq!(
+            thinned,
+            vec![
+                Vector2::new(0.0, 0.0),
+                Vector2::new(1.0, 0.5),
+                Vector2::new(3.0, 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn colors_must_be_hex_triplets() {
+        assert_eq!(parse_color("#1f77B4"), Some([0x1f, 0x77, 0xb4]));
+        assert_eq!(parse_color("#aébbc"), None);
+        assert_eq!(parse_color("1f77b4"), None);
+        assert!(serde_yaml::from_str::<PlotConfig>("colors: {Moon: '#aébbc'}").is_err());
+        let config: PlotConfig = serde_yaml::from_str("colors: {Moon: '#ffffff'}").unwrap();
+        assert_eq!(config.colors["Moon"], "#ffffff");
+    }
+
+    #[test]
+    fn plots_smaller_than_the_margins_are_rejected() {
+        assert!(serde_yaml::from_str::<PlotConfig>("width: 60").is_err());
+        let config: PlotConfig = serde_yaml::from_str("width: 300").unwrap();
+        assert_eq!((config.width, config.height), (300, 1024));
+    }
+
+    #[test]
+    fn trajectories_are_written_as_svg_and_png() {
+        let mut simulation = Simulation::new(Some(0.0), Some(2.0), Some(1.0));
+        simulation.add_body(Body::new(
+            String::from("Moon <1>"),
+            1.0,
+            1.0,
+            Vector3::default(),
+            Vector3::new(1.0, 1.0, 0.0),
+            Default::default(),
+        ));
+        let output_dir = std::env::temp_dir().join(format!("plot_adapter_{}", std::process::id()));
+        let config = PlotConfig {
+            width: 200,
+            height: 100,
+            ..Default::default()
+        };
+        PlotAdapter::new(&simulation)
+            .with_config(config)
+            .with_output_dir(output_dir.clone())
+            .output()
+            .unwrap();
+
+        let svg = fs::read_to_string(output_dir.join("trajectories.svg")).unwrap();
+        assert!(svg.contains("width=\"200\" height=\"100\""));
+        assert!(svg.contains("<polyline fill=\"none\" stroke=\"#1f77b4\""));
+        assert!(svg.contains(">Moon &lt;1&gt;</text>"));
+        let png = image::open(output_dir.join("trajectories.png"))
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(png.dimensions(), (200, 100));
+        // The trajectory runs diagonally through the centre
+        assert_eq!(png.get_pixel(100, 50).0, PALETTE[0]);
+        assert_eq!(png.get_pixel(199, 0).0, BACKGROUND);
+        fs::remove_dir_all(output_dir).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::output_adapter::OutputAdapter;
+use crate::output_adapter::{OutputAdapter, OutputResult};
 use crate::simulation::{Run, Simulation};
 
 pub struct StdoutAdapter<'a, const N: usize> {
@@ -10,10 +10,11 @@ impl<'a, const N: usize> OutputAdapter<'a, N> for StdoutAdapter<'a, N> {
         Self { simulation }
     }
 
-    fn output(&self) {
+    fn output(&self) -> OutputResult {
         let run = Run::from(self.simulation);
         for step in run {
             println!("{}: {:?}", step.t, step.body_map);
         }
+        Ok(())
     }
 }
@@ -0,0 +1,49 @@
+use crate::output_adapter::{OutputAdapter, OutputResult, DEFAULT_OUTPUT_DIR};
+use crate::simulation::{Run, Simulation};
+use crate::trajectory::TrajectoryWriter;
+use std::fs;
+use std::path::PathBuf;
+
+/// Records the run to a binary trajectory file that can be replayed in the
+/// graphical viewer.
+pub struct TrajectoryAdapter<'a, const N: usize> {
+    simulation: &'a Simulation<N>,
+    output_dir: PathBuf,
+}
+
+impl<'a, const N: usize> OutputAdapter<'a, N> for TrajectoryAdapter<'a, N> {
+    fn new(simulation: &'a Simulation<N>) -> Self {
+        Self {
+            simulation,
+            output_dir: PathBuf::from(DEFAULT_OUTPUT_DIR),
+        }
+    }
+
+    fn output(&self) -> OutputResult {
+        if self.simulation.t_end().is_none() {
+            return Err("trajectory output requires the simulation to have a t_end".into());
+        }
+        fs::create_dir_all(&self.output_dir)?;
+
+        let labels = self
+            .simulation
+            .bodies()
+            .iter()
+            .map(|b| b.label.clone())
+            .collect();
+        let path = self.output_dir.join("trajectory.bin");
+        let mut writer = TrajectoryWriter::create::<N>(&path, labels)?;
+        for step in Run::from(self.simulation) {
+            writer.write_step(step.t, &step.body_map)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<'a, const N: usize> TrajectoryAdapter<'a, N> {
+    pub fn with_output_dir(mut self, output_dir: PathBuf) -> Self {
+        self.output_dir = output_dir;
+        self
+    }
+}
@@ -0,0 +1,231 @@
+use crate::output_adapter::{OutputAdapter, OutputResult, DEFAULT_OUTPUT_DIR};
+use crate::simulation::{BodyMap, Run, Simulation};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes each simulation step as a VTK XML unstructured grid (`.vtu`) of
+/// points, along with a ParaView collection (`.pvd`) indexing the steps by
+/// time so that the run can be opened as a single animated dataset.
+pub struct VtkAdapter<'a, const N: usize> {
+    simulation: &'a Simulation<N>,
+    output_dir: PathBuf,
+    name: String,
+}
+
+impl<'a, const N: usize> OutputAdapter<'a, N> for VtkAdapter<'a, N> {
+    fn new(simulation: &'a Simulation<N>) -> Self {
+        Self {
+            simulation,
+            output_dir: PathBuf::from(DEFAULT_OUTPUT_DIR),
+            name: String::from("simulation"),
+        }
+    }
+
+    fn output(&self) -> OutputResult {
+        if self.simulation.t_end().is_none() {
+            return Err("VTK output requires the simulation to have a t_end".into());
+        }
+        fs::create_dir_all(&self.output_dir)?;
+
+        let order: Vec<String> = self
+            .simulation
+            .bodies()
+            .iter()
+            .map(|b| b.label.clone())
+            .collect();
+        let mut datasets = Vec::new();
+        for (i, step) in Run::from(self.simulation).enumerate() {
+            let filename = format!("{}_{:06}.vtu", self.name, i);
+            self.write_step(&self.output_dir.join(&filename), &step.body_map, &order)?;
+            datasets.push((step.t, filename));
+        }
+        self.write_collection(&datasets, &order)
+    }
+}
+
+impl<'a, const N: usize> VtkAdapter<'a, N> {
+    /// Sets the directory the step files and collection are written to,
+    /// creating it if needed.
+    pub fn with_output_dir(mut self, output_dir: PathBuf) -> Self {
+        self.output_dir = output_dir;
+        self
+    }
+
+    fn write_step(&self, path: &Path, body_map: &BodyMap<N>, order: &[String]) -> OutputResult {
+        let bodies: Vec<(usize, _)> = order
+            .iter()
+            .enumerate()
+            .filter_map(|(i, label)| body_map.get(label).map(|b| (i, b)))
+            .collect();
+        let n = bodies.len();
+
+        let mut w = BufWriter::new(File::create(path)?);
+        writeln!(w, "<?xml version=\"1.0\"?>")?;
+        writeln!(
+            w,
+            "<VTKFile type=\"UnstructuredGrid\" version=\"0.1\" byte_order=\"LittleEndian\">"
+        )?;
+        writeln!(w, "  <UnstructuredGrid>")?;
+        writeln!(
+            w,
+            "    <Piece NumberOfPoints=\"{}\" NumberOfCells=\"{}\">",
+            n, n
+        )?;
+
+        writeln!(w, "      <PointData Scalars=\"mass\" Vectors=\"velocity\">")?;
+        Self::write_floats(&mut w, "mass", 1, bodies.iter().map(|(_, b)| b.mass))?;
+        Self::write_floats(
+            &mut w,
+            "diameter",
+            1,
+            bodies.iter().map(|(_, b)| b.diameter),
+        )?;
+        Self::write_floats(
+            &mut w,
+            "velocity",
+            3,
+            bodies.iter().flat_map(|(_, b)| {
+                let v = b.velocity.resized::<3>();
+                [v.x(), v.y(), v.z()]
+            }),
+        )?;
+        Self::write_array(
+            &mut w,
+            "Int32",
+            "label_index",
+            1,
+            bodies.iter().map(|(i, _)| i),
+        )?;
+        writeln!(w, "      </PointData>")?;
+
+        writeln!(w, "      <Points>")?;
+        Self::write_floats(
+            &mut w,
+            "position",
+            3,
+            bodies.iter().flat_map(|(_, b)| {
+                let p = b.position.resized::<3>();
+                [p.x(), p.y(), p.z()]
+            }),
+        )?;
+        writeln!(w, "      </Points>")?;
+
+        // Each point is its own VTK_VERTEX (type 1) cell so that it renders
+        writeln!(w, "      <Cells>")?;
+        Self::write_array(&mut w, "Int32", "connectivity", 1, 0..n)?;
+        Self::write_array(&mut w, "Int32", "offsets", 1, 1..=n)?;
+        Self::write_array(&mut w, "UInt8", "types", 1, (0..n).map(|_| 1))?;
+        writeln!(w, "      </Cells>")?;
+
+        writeln!(w, "    </Piece>")?;
+        writeln!(w, "  </UnstructuredGrid>")?;
+        writeln!(w, "</VTKFile>")?;
+        w.flush()?;
+        Ok(())
+    }
+
+    fn write_floats(
+        w: &mut impl Write,
+        name: &str,
+        components: usize,
+        values: impl Iterator<Item = f32>,
+    ) -> OutputResult {
+        let values = values.map(|v| format!("{:e}", v));
+        Self::write_array(w, "Float32", name, components, values)
+    }
+
+    fn write_array<T: ToString>(
+        w: &mut impl Write,
+        data_type: &str,
+        name: &str,
+        components: usize,
+        values: impl Iterator<Item = T>,
+    ) -> OutputResult {
+        let values: Vec<String> = values.map(|v| v.to_string()).collect();
+        writeln!(
+            w,
+            "        <DataArray type=\"{}\" Name=\"{}\" NumberOfComponents=\"{}\" format=\"ascii\">",
+            data_type, name, components
+        )?;
+        writeln!(w, "          {}", values.join(" "))?;
+        writeln!(w, "        </DataArray>")?;
+        Ok(())
+    }
+
+    fn write_collection(&self, datasets: &[(f32, String)], order: &[String]) -> OutputResult {
+        let path = self.output_dir.join(format!("{}.pvd", self.name));
+        let mut w = BufWriter::new(File::create(path)?);
+        writeln!(w, "<?xml version=\"1.0\"?>")?;
+        writeln!(
+            w,
+            "<VTKFile type=\"Collection\" version=\"0.1\" byte_order=\"LittleEndian\">"
+        )?;
+        for (i, label) in order.iter().enumerate() {
+            // Comments can't contain "--" or end with "-", so hyphens in
+            // labels are written as character references
+            let label = label.replace('-', "&#45;");
+            writeln!(w, "  <!-- label_index {}: {} -->", i, label)?;
+        }
+        writeln!(w, "  <Collection>")?;
+        for (t, filename) in datasets {
+            writeln!(
+                w,
+                "    <DataSet timestep=\"{}\" group=\"\" part=\"0\" file=\"{}\"/>",
+                t, filename
+            )?;
+        }
+        writeln!(w, "  </Collection>")?;
+        writeln!(w, "</VTKFile>")?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector3;
+    use crate::simulation::Body;
+
+    #[test]
+    fn steps_are_written_as_points_indexed_by_a_collection() {
+        let mut simulation = Simulation::new(Some(0.0), Some(1.0), Some(1.0));
+        for (label, x) in [("Moon--1", 2.0), ("Earth", 0.0)] {
+            simulation.add_body(Body::new(
+                String::from(label),
+                1.0,
+                0.5,
+                Vector3::new(x, 0.0, 0.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                Default::default(),
+            ));
+        }
+        let output_dir = std::env::temp_dir().join(format!("vtk_adapter_{}", std::process::id()));
+        VtkAdapter::new(&simulation)
+            .with_output_dir(output_dir.clone())
+            .output()
+            .unwrap();
+
+        let collection = fs::read_to_string(output_dir.join("simulation.pvd")).unwrap();
+        assert!(collection
+            .contains("timestep=\"0\" group=\"\" part=\"0\" file=\"simulation_000000.vtu\""));
+        assert!(collection
+            .contains("timestep=\"1\" group=\"\" part=\"0\" file=\"simulation_000001.vtu\""));
+        assert!(collection.contains("<!-- label_index 0: Moon&#45;&#45;1 -->"));
+        assert!(collection.contains("<!-- label_index 1: Earth -->"));
+
+        let first = fs::read_to_string(output_dir.join("simulation_000000.vtu")).unwrap();
+        assert!(first.contains("NumberOfPoints=\"2\" NumberOfCells=\"2\""));
+        let array = |name: &str| {
+            let start = first.find(&format!("Name=\"{}\"", name)).unwrap();
+            first[start..].lines().nth(1).unwrap().trim().to_string()
+        };
+        assert_eq!(array("mass"), "1e0 1e0");
+        assert_eq!(array("position"), "2e0 0e0 0e0 0e0 0e0 0e0");
+        assert_eq!(array("velocity"), "0e0 0e0 1e0 0e0 0e0 1e0");
+        assert_eq!(array("label_index"), "0 1");
+        assert!(output_dir.join("simulation_000001.vtu").exists());
+        fs::remove_dir_all(output_dir).unwrap();
+    }
+}
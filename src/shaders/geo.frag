@@ -1,5 +1,8 @@
 #version 330 core
 
+#define MAX_LIGHTS 4
+#define MAX_OCCLUDERS 16
+
 in vec3 Normal;
 in vec3 FragPos;
 in vec2 TexCoord;
@@ -7,19 +10,79 @@ in vec2 TexCoord;
 out vec4 FragColor;
 
 uniform vec3 light_color;
-uniform vec3 light_pos;
+uniform float emissive;
+// Lit from either side, for flat shapes such as rings
+uniform float double_sided;
+uniform int num_lights;
+uniform int num_occluders;
+// Spheres as centre in xyz and radius in w
+uniform vec4 lights[MAX_LIGHTS];
+uniform vec4 occluders[MAX_OCCLUDERS];
 uniform sampler2D textureSource;
 
+// Approximate fraction of a disc of angular radius a covered by a disc of
+// angular radius b whose centre is the given angle away, as in
+// graphics/lighting.rs
+float coverage(float a, float b, float separation) {
+    float full = a > 0.0 ? min(b * b / (a * a), 1.0) : 1.0;
+    float inner = abs(a - b);
+    float outer = a + b;
+    if (separation >= outer) {
+        return 0.0;
+    }
+    if (separation <= inner) {
+        return full;
+    }
+    return full * (1.0 - smoothstep(inner, outer, separation));
+}
+
+// Fraction of the light visible from the fragment past the occluders
+float visibility(vec4 light) {
+    vec3 toLight = light.xyz - FragPos;
+    float lightDistance = length(toLight);
+    float lightSize = asin(min(light.w / lightDistance, 1.0));
+    float visible = 1.0;
+    for (int i = 0; i < num_occluders; i++) {
+        vec3 toOccluder = occluders[i].xyz - FragPos;
+        float occluderDistance = length(toOccluder);
+        // Skip the fragment's own body and anything beyond the light
+        if (occluderDistance <= occluders[i].w * 1.001 || occluderDistance >= lightDistance) {
+            continue;
+        }
+        float occluderSize = asin(occluders[i].w / occluderDistance);
+        float cosSeparation = dot(toLight, toOccluder) / (lightDistance * occluderDistance);
+        float separation = acos(clamp(cosSeparation, -1.0, 1.0));
+        visible *= 1.0 - coverage(lightSize, occluderSize, separation);
+    }
+    return visible;
+}
+
 void main() {
+    vec4 texColor = texture(textureSource, TexCoord);
+    // Leave the depth of fully transparent parts, such as gaps in rings,
+    // to what's behind them
+    if (texColor.a < 0.01) {
+        discard;
+    }
+    if (emissive > 0.5) {
+        FragColor = texColor;
+        return;
+    }
+
     float ambientStrength = 0.1;
     vec4 ambient = vec4(ambientStrength * light_color, 1.0);
-    vec4 texColor = texture(textureSource, TexCoord);
 
     vec3 norm = normalize(Normal);
-    vec3 lightDir = normalize(light_pos - FragPos);
-
-    float diffStrength = max(dot(norm, lightDir), 0.0);
-    vec4 diffuse = diffStrength * vec4(light_color, 1.0);
+    vec4 diffuse = vec4(0.0);
+    for (int i = 0; i < num_lights; i++) {
+        vec3 lightDir = normalize(lights[i].xyz - FragPos);
+        float facing = dot(norm, lightDir);
+        float diffStrength = max(double_sided > 0.5 ? abs(facing) : facing, 0.0);
+        if (diffStrength > 0.0) {
+            diffStrength *= visibility(lights[i]);
+        }
+        diffuse += diffStrength * vec4(light_color, 1.0);
+    }
 
-    FragColor = (ambient + diffuse) * texColor;
-}
\ No newline at end of file
+    FragColor = vec4(((ambient + diffuse) * texColor).rgb, texColor.a);
+}
@@ -2,19 +2,19 @@
 in vec3 pos;
 in vec3 normal;
 in vec2 tex_coord;
+in mat4 inst_model;
+in mat4 inst_normal_mat;
 
-uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
-uniform mat4 normal_mat;
 
 out vec3 Normal;
 out vec3 FragPos;
 out vec2 TexCoord;
 
 void main() {
-    FragPos = vec3(model * vec4(pos, 1.0));
-    Normal = mat3(normal_mat) * normal;
+    FragPos = vec3(inst_model * vec4(pos, 1.0));
+    Normal = mat3(inst_normal_mat) * normal;
     gl_Position = projection * view * vec4(FragPos, 1.0);
     TexCoord = tex_coord;
 }
\ No newline at end of file
@@ -0,0 +1,12 @@
+#version 330 core
+in vec3 pos;
+in vec4 color;
+
+uniform mat4 view_projection;
+
+out vec4 Color;
+
+void main() {
+    gl_Position = view_projection * vec4(pos, 1.0);
+    Color = color;
+}
@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec2 Corner;
+in vec4 Color;
+
+out vec4 FragColor;
+
+void main() {
+    // Round off the corners of the quad
+    if (dot(Corner, Corner) > 1.0) {
+        discard;
+    }
+    FragColor = Color;
+}
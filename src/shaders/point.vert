@@ -0,0 +1,21 @@
+#version 330 core
+in vec3 pos;
+in vec2 corner;
+in float size;
+in vec4 color;
+
+uniform mat4 view_projection;
+uniform vec2 screen_size;
+
+out vec2 Corner;
+out vec4 Color;
+
+void main() {
+    // Offset the corners of the quad in screen space, so points keep the
+    // same size in pixels at any distance
+    vec4 clip = view_projection * vec4(pos, 1.0);
+    clip.xy += corner * size / screen_size * clip.w;
+    gl_Position = clip;
+    Corner = corner;
+    Color = color;
+}
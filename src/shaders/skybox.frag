@@ -0,0 +1,17 @@
+#version 330 core
+
+#define PI 3.1415926535897932384626433832795
+
+in vec3 Direction;
+
+out vec4 FragColor;
+
+uniform sampler2D sky;
+
+void main() {
+    // Look up the direction in the equirectangular image, with longitude
+    // along u and latitude along v
+    vec3 dir = normalize(Direction);
+    vec2 uv = vec2(0.5 + atan(dir.z, dir.x) / (2.0 * PI), 0.5 - asin(dir.y) / PI);
+    FragColor = texture(sky, uv);
+}
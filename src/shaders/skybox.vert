@@ -0,0 +1,13 @@
+#version 330 core
+in vec3 pos;
+
+// Projection and view with the camera's translation removed, so the sky
+// stays infinitely far away
+uniform mat4 view_projection;
+
+out vec3 Direction;
+
+void main() {
+    gl_Position = view_projection * vec4(pos, 1.0);
+    Direction = pos;
+}
@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec2 TexCoord;
+in vec4 Color;
+
+out vec4 FragColor;
+
+uniform sampler2D glyphs;
+
+void main() {
+    FragColor = vec4(Color.rgb, Color.a * texture(glyphs, TexCoord).a);
+}
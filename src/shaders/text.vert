@@ -0,0 +1,17 @@
+#version 330 core
+in vec2 pos;
+in vec2 tex_coord;
+in vec4 color;
+
+uniform vec2 screen_size;
+
+out vec2 TexCoord;
+out vec4 Color;
+
+void main() {
+    // Positions are in pixels from the top left of the screen
+    vec2 ndc = vec2(2.0 * pos.x / screen_size.x - 1.0, 1.0 - 2.0 * pos.y / screen_size.y);
+    gl_Position = vec4(ndc, 0.0, 1.0);
+    TexCoord = tex_coord;
+    Color = color;
+}
@@ -1,20 +1,161 @@
-use crate::force::{ForceVector, Gravity};
-use crate::math::vector::{Distance, Vector};
-use serde::{Deserialize, Serialize};
+use crate::connection::{add_spring_forces, apply_rods, Connection};
+use crate::force::{
+    Coulomb, Drag, Force, ForceMap, ForceVector, Gravity, Lorentz, RadiationPressure, Relativity,
+    Tides, TorqueMap, TorqueVector,
+};
+use crate::maneuver::{orbital_axes, FuelRecord, Maneuver, STANDARD_GRAVITY};
+use crate::math::quaternion::Quaternion;
+use crate::math::vector::{Distance, Vector, Vector3};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::BTreeMap;
 use std::mem;
 
 pub type PositionVector<const N: usize> = Vector<N>;
 pub type VelocityVector<const N: usize> = Vector<N>;
 
+/// Initial rotation of a body, as a spin about its y axis tilted about
+/// the -z axis
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Default)]
 pub struct SpinCharacteristics<const N: usize> {
+    /// Angle between the spin axis and the y axis, in degrees
     pub tilt: f32,
+    /// Angular velocity about the spin axis, in radians per second
     pub velocity: f32,
+    /// Initial angle about the spin axis, in radians
     pub angle: f32,
+    /// Initial orientation, overriding `tilt` and `angle`
+    #[serde(default)]
+    pub orientation: Option<Quaternion>,
+    /// Initial angular velocity vector, overriding `velocity` about the
+    /// tilted axis
+    #[serde(default)]
+    pub angular_velocity: Option<Vector3>,
+}
+
+/// Zonal harmonics of a body's gravity, the dimensionless coefficients of
+/// its departure from a point mass symmetric about its spin axis
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GravityField {
+    /// Oblateness, positive for a body flattened at the poles
+    #[serde(default)]
+    pub j2: f32,
+    /// Pear shaped asymmetry between the hemispheres
+    #[serde(default)]
+    pub j3: f32,
+    #[serde(default)]
+    pub j4: f32,
+    /// Radius the coefficients are normalized to, defaulting to half the
+    /// body's diameter
+    #[serde(default)]
+    pub reference_radius: Option<f32>,
+}
+
+/// Density profile of a body's atmosphere, by altitude above its surface
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Atmosphere {
+    /// Density falling off exponentially from `density` at the surface,
+    /// by a factor of e every `scale_height`, up to an optional `ceiling`
+    Exponential {
+        density: f32,
+        scale_height: f32,
+        #[serde(default)]
+        ceiling: Option<f32>,
+    },
+    /// Densities at increasing altitudes, interpolated exponentially
+    /// between them. There's no atmosphere above the last.
+    Tabulated(Vec<(f32, f32)>),
+}
+
+impl Atmosphere {
+    /// Density at `altitude` above the surface
+    pub fn density(&self, altitude: f32) -> f32 {
+        let altitude = altitude.max(0.0);
+        match self {
+            Self::Exponential {
+                density,
+                scale_height,
+                ceiling,
+            } => {
+                if ceiling.is_some_and(|ceiling| altitude > ceiling) {
+                    0.0
+                } else {
+                    density * (-altitude / scale_height).exp()
+                }
+            }
+            Self::Tabulated(table) => {
+                let above = table.partition_point(|&(a, _)| a <= altitude);
+                if above == table.len() {
+                    return 0.0;
+                }
+                let (a1, d1) = table[above];
+                let (a0, d0) = match above {
+                    0 => return d1,
+                    _ => table[above - 1],
+                };
+                let t = (altitude - a0) / (a1 - a0);
+                if d0 > 0.0 && d1 > 0.0 {
+                    d0 * (d1 / d0).powf(t)
+                } else {
+                    d0 + (d1 - d0) * t
+                }
+            }
+        }
+    }
+}
+
+/// Orientation of a body and how fast it's rotating
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Attitude {
+    /// Rotation from the body's own axes to the simulation's
+    pub orientation: Quaternion,
+    /// Angular velocity in radians per second, in the simulation's axes
+    pub angular_velocity: Vector3,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl<const N: usize> From<&SpinCharacteristics<N>> for Attitude {
+    fn from(spin: &SpinCharacteristics<N>) -> Self {
+        let tilt =
+            Quaternion::from_axis_angle(&Vector3::new(0.0, 0.0, -1.0), spin.tilt.to_radians());
+        let angle = Quaternion::from_axis_angle(&Vector3::new(0.0, 1.0, 0.0), spin.angle);
+        Self {
+            orientation: spin.orientation.unwrap_or(tilt * angle),
+            angular_velocity: spin
+                .angular_velocity
+                .unwrap_or_else(|| tilt.rotate(&Vector3::new(0.0, spin.velocity, 0.0))),
+        }
+    }
+}
+
+impl Attitude {
+    /// Integrates Euler's equations for a rigid body with the given
+    /// principal moments of inertia under a torque, in the simulation's
+    /// axes, over a time step. Axes with no moment of inertia keep their
+    /// angular velocity.
+    pub fn step(&mut self, inertia: &Vector3, torque: &Vector3, t_step: f32) {
+        let to_body = self.orientation.conjugate();
+        let omega = to_body.rotate(&self.angular_velocity);
+        let torque = to_body.rotate(torque);
+        let momentum = Vector3::new(
+            inertia[0] * omega[0],
+            inertia[1] * omega[1],
+            inertia[2] * omega[2],
+        );
+        let gyroscopic = omega.cross(&momentum);
+        let mut angular_acceleration = Vector3::default();
+        for i in 0..3 {
+            if inertia[i] > 0.0 {
+                angular_acceleration[i] = (torque[i] - gyroscopic[i]) / inertia[i];
+            }
+        }
+        let omega = &omega + &(t_step * &angular_acceleration);
+        let rotation = Quaternion::from_rotation_vector(&(t_step * &omega));
+        self.orientation = (self.orientation * rotation).normalize();
+        self.angular_velocity = self.orientation.rotate(&omega);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Body<const N: usize> {
     pub label: String,
     pub mass: f32,
@@ -25,9 +166,57 @@ pub struct Body<const N: usize> {
     pub velocity: VelocityVector<N>,
     #[serde(default)]
     pub spin: SpinCharacteristics<N>,
+    /// Principal moments of inertia about the body's x, y and z axes, with
+    /// y the spin axis. Defaults to those of a uniform sphere.
+    #[serde(default)]
+    pub inertia: Option<Vector3>,
+    /// Love number, how much the body deforms under tides. Bodies without
+    /// one are rigid.
+    #[serde(default)]
+    pub k2: Option<f32>,
+    /// Time in seconds the body's tidal bulge lags behind the tide raising
+    /// body, which dissipates energy
+    #[serde(default)]
+    pub tidal_lag: Option<f32>,
+    /// Zonal harmonics about the spin axis, for bodies whose gravity isn't
+    /// that of a point mass
+    #[serde(default)]
+    pub gravity_field: Option<GravityField>,
+    /// Atmosphere that drags on bodies passing through it, rotating with
+    /// the body
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub atmosphere: Option<Atmosphere>,
+    /// Drag coefficient of the body in other bodies' atmospheres, by
+    /// default that of a typical satellite
+    #[serde(default)]
+    pub drag_coefficient: Option<f32>,
+    /// Power radiated by the body, in watts
+    #[serde(default)]
+    pub luminosity: Option<f32>,
+    /// Fraction of the light falling on the body that it reflects rather
+    /// than absorbs, which doubles the push from the reflected part
+    #[serde(default)]
+    pub reflectivity: Option<f32>,
+    /// Electric charge, in coulombs
+    #[serde(default)]
+    pub charge: Option<f32>,
+    /// Burns the body makes, for spacecraft
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub maneuvers: Vec<Maneuver>,
+    /// Mass left once all the fuel for burns is spent, required by
+    /// maneuvers with a specific impulse
+    #[serde(default)]
+    pub dry_mass: Option<f32>,
 
+    #[serde(skip)]
+    pub fuel_record: FuelRecord,
     #[serde(skip)]
     pub forces: Vec<ForceVector<N>>,
+    #[serde(skip)]
+    pub torques: Vec<TorqueVector>,
+    /// Current orientation and angular velocity, starting from `spin`
+    #[serde(skip)]
+    pub attitude: Attitude,
 }
 
 impl<const N: usize> Body<N> {
@@ -46,22 +235,152 @@ impl<const N: usize> Body<N> {
             position,
             velocity,
             spin,
+            inertia: None,
+            k2: None,
+            tidal_lag: None,
+            gravity_field: None,
+            atmosphere: None,
+            drag_coefficient: None,
+            luminosity: None,
+            reflectivity: None,
+            charge: None,
+            maneuvers: Vec::new(),
+            dry_mass: None,
+            fuel_record: FuelRecord::default(),
             forces: Vec::new(),
+            torques: Vec::new(),
+            attitude: Attitude::from(&spin),
         }
     }
 
+    /// Principal moments of inertia, given or of a uniform sphere
+    pub fn moments_of_inertia(&self) -> Vector3 {
+        self.inertia.unwrap_or_else(|| {
+            let moment = 0.4 * self.mass * (self.diameter / 2.0).powi(2);
+            Vector3::new(moment, moment, moment)
+        })
+    }
+
     fn apply_forces(&mut self, t_step: f32) {
         let net_force: Vector<N> = self.forces.iter().map(|f| f.v).sum();
-        let acceleration = net_force.magnitude() / self.mass;
-        let acceleration_vector = acceleration * &net_force.normalize();
+        let acceleration_vector = (1.0 / self.mass) * &net_force;
         let displacement =
             &(t_step * &self.velocity) + &(0.5 * t_step.powi(2) * &acceleration_vector);
         self.position = &self.position + &displacement;
         self.velocity = &self.velocity + &(t_step * &acceleration_vector);
     }
 
+    /// Applies any impulses during the step from `t` and adds the thrust
+    /// of any burns to the body's forces, returning the mass of fuel they
+    /// burn
+    fn apply_maneuvers(&mut self, body_map: &BodyMap<N>, t: f32, t_step: f32) -> f32 {
+        let dry_mass = self.dry_mass.unwrap_or_default();
+        let mut fuel = 0.0;
+        for (i, maneuver) in self.maneuvers.iter().enumerate() {
+            let starting = maneuver.starts_within(t, t_step);
+            let axes = match maneuver.relative_to() {
+                Some(label) => match body_map.get(label) {
+                    Some(reference) => Some(orbital_axes(self, reference)),
+                    None => {
+                        if starting {
+                            eprintln!(
+                                "warning: maneuver of {} is relative to unknown body {}",
+                                self.label, label
+                            );
+                        }
+                        None
+                    }
+                },
+                None => None,
+            };
+            let to_simulation_axes = |v: &Vector3| match &axes {
+                Some([prograde, normal, radial]) => {
+                    &(&(v.x() * prograde) + &(v.y() * normal)) + &(v.z() * radial)
+                }
+                None => *v,
+            };
+            // Fuel is burnt until the body is down to its dry mass
+            let mass = self.mass - fuel;
+            let available = match self.fuel_record.burnout {
+                Some(_) => 0.0,
+                None => (mass - dry_mass).max(0.0),
+            };
+            match maneuver {
+                Maneuver::Impulse { delta_v, isp, .. } if starting => {
+                    // Running in reverse takes the impulse back off
+                    let mut delta_v = t_step.signum() * &to_simulation_axes(delta_v);
+                    if let Some(isp) = isp {
+                        // Rocket equation, m₁ = m₀ exp(-Δv / vₑ)
+                        let exhaust_velocity = isp * STANDARD_GRAVITY;
+                        let ratio =
+                            (-t_step.signum() * delta_v.magnitude() / exhaust_velocity).exp();
+                        let mut burnt = mass * (1.0 - ratio);
+                        // Short of fuel, the impulse is cut to what's left,
+                        // and only that much is taken back in reverse
+                        if t_step > 0.0 && burnt > available {
+                            burnt = available;
+                            let reachable = exhaust_velocity * (mass / (mass - burnt)).ln();
+                            let fraction = reachable / delta_v.magnitude();
+                            delta_v = fraction * &delta_v;
+                            self.fuel_record.cut_impulses.insert(i, fraction);
+                            self.fuel_record.burnout = Some(maneuver.t());
+                        } else if t_step < 0.0 {
+                            if let Some(fraction) = self.fuel_record.cut_impulses.remove(&i) {
+                                delta_v = fraction * &delta_v;
+                                let ratio = (delta_v.magnitude() / exhaust_velocity).exp();
+                                burnt = mass * (1.0 - ratio);
+                            }
+                        }
+                        fuel += burnt;
+                    }
+                    self.velocity = &self.velocity + &delta_v.resized::<N>();
+                }
+                Maneuver::Burn {
+                    thrust,
+                    isp,
+                    direction,
+                    ..
+                } if maneuver.is_burning(t, t_step) && (t_step < 0.0 || available > 0.0) => {
+                    let mut thrust = *thrust;
+                    let mut burnt = thrust / (isp * STANDARD_GRAVITY) * t_step;
+                    // The last of the fuel runs out part way through the
+                    // step, so the thrust is averaged over the whole of it.
+                    // In reverse, only the thrust before then is undone.
+                    let fraction = match self.fuel_record.burnout {
+                        _ if t_step > 0.0 => (available / burnt).min(1.0),
+                        Some(burnout) => ((burnout - (t + t_step)) / -t_step).clamp(0.0, 1.0),
+                        None => 1.0,
+                    };
+                    if t_step > 0.0 && fraction < 1.0 {
+                        self.fuel_record.burnout = Some(t + fraction * t_step);
+                    }
+                    thrust *= fraction;
+                    burnt *= fraction;
+                    let direction = to_simulation_axes(direction).normalize();
+                    self.forces.push(ForceVector {
+                        label: String::from("thrust"),
+                        v: (thrust * &direction).resized::<N>(),
+                    });
+                    fuel += burnt;
+                }
+                _ => {}
+            }
+        }
+        // Reversing back past the time the fuel ran out refuels the body
+        if self
+            .fuel_record
+            .burnout
+            .is_some_and(|burnout| t_step < 0.0 && t + t_step <= burnout)
+        {
+            self.fuel_record.burnout = None;
+        }
+        fuel
+    }
+
     fn apply_spin(&mut self, t_step: f32) {
-        self.spin.angle = self.spin.angle + (t_step * self.spin.velocity);
+        let torque: Vector3 = self.torques.iter().map(|t| t.v).sum();
+        let inertia = self.moments_of_inertia();
+        self.attitude.step(&inertia, &torque, t_step);
     }
 }
 
@@ -70,50 +389,150 @@ pub type BodyMap<const N: usize> = BTreeMap<String, Body<N>>;
 fn body_map_from_bodies<'a, const N: usize>(bodies: &'a Vec<Body<N>>) -> BodyMap<N> {
     let mut body_map = BodyMap::new();
     for body in bodies {
-        body_map.insert(
-            String::from(&body.label),
-            Body::new(
-                body.label.clone(),
-                body.mass,
-                body.diameter,
-                body.position,
-                body.velocity,
-                body.spin,
-            ),
-        );
+        // Start from the configured state rather than any left over from
+        // a previous run
+        let new_body = Body {
+            forces: Vec::new(),
+            torques: Vec::new(),
+            attitude: Attitude::from(&body.spin),
+            ..body.clone()
+        };
+        body_map.insert(String::from(&body.label), new_body);
     }
     body_map
 }
 
-fn compute_next_step<const N: usize>(body_map: &BodyMap<N>, t_step: f32) -> BodyMap<N> {
-    let g = Gravity::new(None);
+fn compute_next_step<const N: usize>(
+    simulation: &Simulation<N>,
+    body_map: &BodyMap<N>,
+    t: f32,
+    t_step: f32,
+) -> BodyMap<N> {
+    let constants = &simulation.constants;
+    let g = Gravity::new(constants.g);
+    let tides = Tides::new(constants.g);
     let mut new_body_map = BodyMap::new();
     let mut bodies = Vec::new();
     for body in body_map.values() {
         bodies.push(body);
     }
-    let mut force_map = g.forces_from_bodies(&bodies);
+    let mut force_map = ForceMap::new();
+    let mut torque_map = TorqueMap::new();
+    g.add_forces(&bodies, &mut force_map, &mut torque_map);
+    tides.add_forces(&bodies, &mut force_map, &mut torque_map);
+    Drag.add_forces(&bodies, &mut force_map, &mut torque_map);
+    let radiation = RadiationPressure::new(constants.c);
+    radiation.add_forces(&bodies, &mut force_map, &mut torque_map);
+    let coulomb = Coulomb::new(constants.k);
+    coulomb.add_forces(&bodies, &mut force_map, &mut torque_map);
+    let fields = &simulation.fields;
+    Lorentz::new(fields.electric, fields.magnetic).add_forces(&bodies, &mut force_map);
+    add_spring_forces(&simulation.connections, body_map, &mut force_map);
+    if simulation.relativity {
+        let relativity = Relativity::new(constants.g, constants.c);
+        relativity.add_forces(&bodies, &mut force_map, &mut torque_map);
+    }
     for body in bodies {
         let mut new_body = Body {
             label: body.label.clone(),
             forces: force_map.remove(&body.label).unwrap_or_default(),
-            ..*body
+            torques: torque_map.remove(&body.label).unwrap_or_default(),
+            ..body.clone()
         };
 
+        let fuel = new_body.apply_maneuvers(body_map, t, t_step);
         new_body.apply_forces(t_step);
+        new_body.mass -= fuel;
         new_body.apply_spin(t_step);
         new_body_map.insert(body.label.clone(), new_body);
     }
+    apply_rods(&simulation.connections, body_map, &mut new_body_map, t_step);
 
     new_body_map
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Physical constants, for simulations in units other than SI
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Constants {
+    /// Gravitational constant
+    #[serde(default)]
+    pub g: Option<f32>,
+    /// Speed of light, in units consistent with `g`
+    #[serde(default)]
+    pub c: Option<f32>,
+    /// Coulomb constant, in units consistent with bodies' charges
+    #[serde(default)]
+    pub k: Option<f32>,
+}
+
+/// Electric and magnetic fields, uniform throughout the simulation
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Fields {
+    #[serde(default)]
+    pub electric: Vector3,
+    #[serde(default)]
+    pub magnetic: Vector3,
+}
+
+fn deserialize_bodies<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<Vec<Body<N>>, D::Error> {
+    let bodies = Vec::<Body<N>>::deserialize(deserializer)?;
+    for body in &bodies {
+        if body.dry_mass.is_none() && body.maneuvers.iter().any(Maneuver::burns_fuel) {
+            return Err(serde::de::Error::custom(format!(
+                "{} has maneuvers that burn fuel but no dry_mass",
+                body.label
+            )));
+        }
+    }
+    Ok(bodies)
+}
+
+// Deserialized through the impl below, which checks the connections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct Simulation<const N: usize> {
+    #[serde(deserialize_with = "deserialize_bodies")]
     bodies: Vec<Body<N>>,
     t_start: f32,
     t_end: Option<f32>,
     t_step: f32,
+    #[serde(default)]
+    constants: Constants,
+    /// Whether to correct gravity for general relativity
+    #[serde(default)]
+    relativity: bool,
+    /// Fields acting on charged bodies
+    #[serde(default)]
+    fields: Fields,
+    /// Springs and rods joining pairs of bodies
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    connections: Vec<Connection>,
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Simulation<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let simulation = Self::deserialize(deserializer)?;
+        for connection in &simulation.connections {
+            let [a, b] = connection.bodies();
+            for label in [a, b] {
+                if !simulation.bodies.iter().any(|body| &body.label == label) {
+                    return Err(serde::de::Error::custom(format!(
+                        "connection between {} and {} names unknown body {}",
+                        a, b, label
+                    )));
+                }
+            }
+        }
+        Ok(simulation)
+    }
+}
+
+impl<const N: usize> Serialize for Simulation<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Self::serialize(self, serializer)
+    }
 }
 
 impl<const N: usize> Simulation<N> {
@@ -123,9 +542,39 @@ impl<const N: usize> Simulation<N> {
             t_start: t_start.unwrap_or(0.0),
             t_end: t_end,
             t_step: t_step.unwrap_or(0.1),
+            constants: Constants::default(),
+            relativity: false,
+            fields: Fields::default(),
+            connections: Vec::new(),
         }
     }
 
+    /// Uses the given constants instead of their SI values
+    pub fn with_constants(mut self, constants: Constants) -> Self {
+        self.constants = constants;
+        self
+    }
+
+    /// Adds the first post-Newtonian correction to gravity
+    pub fn with_relativity(mut self) -> Self {
+        self.relativity = true;
+        self
+    }
+
+    pub fn with_fields(mut self, fields: Fields) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    pub fn with_connections(mut self, connections: Vec<Connection>) -> Self {
+        self.connections = connections;
+        self
+    }
+
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
     pub fn add_body(&mut self, body: Body<N>) {
         self.bodies.push(body)
     }
@@ -137,6 +586,14 @@ impl<const N: usize> Simulation<N> {
     pub fn bodies(&self) -> &Vec<Body<N>> {
         &self.bodies
     }
+
+    pub fn t_start(&self) -> f32 {
+        self.t_start
+    }
+
+    pub fn t_end(&self) -> Option<f32> {
+        self.t_end
+    }
 }
 
 pub struct RunStep<const N: usize> {
@@ -169,7 +626,12 @@ impl<'a, const N: usize> Iterator for Run<'a, N> {
                 return None;
             }
         }
-        let next_body_map = compute_next_step(&self.body_map, self.simulation.t_step);
+        let next_body_map = compute_next_step(
+            self.simulation,
+            &self.body_map,
+            self.t_current,
+            self.simulation.t_step,
+        );
         let t = self.t_current;
         self.t_current += self.simulation.t_step;
         Some(Self::Item {
@@ -179,25 +641,60 @@ impl<'a, const N: usize> Iterator for Run<'a, N> {
     }
 }
 
-/// Version of a simulation run that takes ownership of the simulation
+/// Version of a simulation run that takes ownership of the simulation.
+/// Cloning it gives an independent run that continues from the same state.
+#[derive(Clone)]
 pub struct OwningRun<const N: usize> {
     simulation: Simulation<N>,
     t_current: f32,
+    t_step: f32,
     body_map: BodyMap<N>,
 }
 
 impl<const N: usize> From<Simulation<N>> for OwningRun<N> {
     fn from(simulation: Simulation<N>) -> Self {
         let t_current = simulation.t_start;
+        let t_step = simulation.t_step;
         let body_map = simulation.create_body_map();
         Self {
             simulation,
             t_current,
+            t_step,
             body_map,
         }
     }
 }
 
+impl<const N: usize> OwningRun<N> {
+    pub fn simulation(&self) -> &Simulation<N> {
+        &self.simulation
+    }
+
+    /// Time step of the run, which is negative when running in reverse
+    pub fn t_step(&self) -> f32 {
+        self.t_step
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.t_step < 0.0
+    }
+
+    /// Reverses the direction of time. The integrator is stepped with a
+    /// negated time step, which retraces the run up to its truncation error.
+    pub fn reverse(&mut self) {
+        self.t_step = -self.t_step;
+        // The state after the last returned step has already been computed,
+        // so step it back so that the last returned state is repeated.
+        self.body_map = compute_next_step(
+            &self.simulation,
+            &self.body_map,
+            self.t_current,
+            self.t_step,
+        );
+        self.t_current += self.t_step;
+    }
+}
+
 impl<const N: usize> Iterator for OwningRun<N> {
     type Item = RunStep<N>;
 
@@ -207,12 +704,236 @@ impl<const N: usize> Iterator for OwningRun<N> {
                 return None;
             }
         }
-        let next_body_map = compute_next_step(&self.body_map, self.simulation.t_step);
+        // Running in reverse stops at the start, allowing for the rounding
+        // accumulated in t_current
+        if self.is_reversed() && self.t_current < self.simulation.t_start + self.t_step / 2.0 {
+            return None;
+        }
+        let next_body_map = compute_next_step(
+            &self.simulation,
+            &self.body_map,
+            self.t_current,
+            self.t_step,
+        );
         let t = self.t_current;
-        self.t_current += self.simulation.t_step;
+        self.t_current += self.t_step;
         Some(Self::Item {
             t,
             body_map: mem::replace(&mut self.body_map, next_body_map),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    fn assert_vectors_eq(a: &Vector3, b: &Vector3, epsilon: f32) {
+        for i in 0..3 {
+            assert_abs_diff_eq!(a[i], b[i], epsilon = epsilon);
+        }
+    }
+
+    #[test]
+    fn spin_characteristics_map_to_a_tilted_attitude() {
+        let spin = SpinCharacteristics::<3> {
+            tilt: 90.0,
+            velocity: 2.0,
+            angle: 0.5,
+            ..Default::default()
+        };
+        let attitude = Attitude::from(&spin);
+        // Tilting the y axis a quarter turn about -z points it along x
+        assert_vectors_eq(
+            &attitude.angular_velocity,
+            &Vector3::new(2.0, 0.0, 0.0),
+            1e-6,
+        );
+        let (_, angle) = attitude.orientation.to_axis_angle();
+        assert!(angle > std::f32::consts::FRAC_PI_2);
+    }
+
+    #[test]
+    fn attitude_steps_follow_eulers_equations() {
+        let spin = SpinCharacteristics::<3> {
+            velocity: 1.0,
+            ..Default::default()
+        };
+        let inertia = Vector3::new(2.0, 2.0, 2.0);
+
+        // Without torque a sphere keeps spinning about the same axis
+        let mut attitude = Attitude::from(&spin);
+        for _ in 0..100 {
+            attitude.step(&inertia, &Vector3::default(), 0.01);
+        }
+        assert_vectors_eq(
+            &attitude.angular_velocity,
+            &Vector3::new(0.0, 1.0, 0.0),
+            1e-5,
+        );
+        let (axis, angle) = attitude.orientation.to_axis_angle();
+        assert_vectors_eq(&axis, &Vector3::new(0.0, 1.0, 0.0), 1e-5);
+        assert_abs_diff_eq!(angle, 1.0, epsilon = 1e-4);
+
+        // A torque along the spin axis spins it up by torque / inertia
+        let mut attitude = Attitude::from(&spin);
+        attitude.step(&inertia, &Vector3::new(0.0, 4.0, 0.0), 0.5);
+        assert_vectors_eq(
+            &attitude.angular_velocity,
+            &Vector3::new(0.0, 2.0, 0.0),
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn charges_gyrate_in_a_magnetic_field() {
+        // Radius m v / (q B) = 1 about (0, 0, 1), half way round in π
+        let mut simulation = Simulation::new(Some(0.0), Some(std::f32::consts::PI), Some(1e-3))
+            .with_fields(Fields {
+                magnetic: Vector3::new(0.0, 1.0, 0.0),
+                ..Default::default()
+            });
+        let mut particle = Body::new(
+            String::from("particle"),
+            1.0,
+            0.0,
+            Vector3::default(),
+            Vector3::new(1.0, 0.0, 0.0),
+            Default::default(),
+        );
+        particle.charge = Some(1.0);
+        simulation.add_body(particle);
+        let body_map = Run::from(&simulation).last().unwrap().body_map;
+        assert_vectors_eq(
+            &body_map["particle"].position,
+            &Vector3::new(0.0, 0.0, 2.0),
+            1e-2,
+        );
+    }
+
+    #[test]
+    fn atmospheres_thin_with_altitude() {
+        let exponential = Atmosphere::Exponential {
+            density: 1.2,
+            scale_height: 8000.0,
+            ceiling: Some(100000.0),
+        };
+        assert_eq!(exponential.density(-10.0), 1.2);
+        assert_abs_diff_eq!(exponential.density(8000.0), 1.2 / std::f32::consts::E);
+        assert_eq!(exponential.density(100001.0), 0.0);
+
+        let tabulated = Atmosphere::Tabulated(vec![(0.0, 1.0), (100.0, 0.01), (200.0, 0.0)]);
+        assert_abs_diff_eq!(tabulated.density(50.0), 0.1, epsilon = 1e-6);
+        assert_abs_diff_eq!(tabulated.density(150.0), 0.005, epsilon = 1e-6);
+        assert_eq!(tabulated.density(250.0), 0.0);
+    }
+
+    /// Periapsis advance per orbit predicted by general relativity, in
+    /// radians
+    fn relativistic_precession(mu: f64, c: f64, a: f64, e: f64) -> f64 {
+        6.0 * std::f64::consts::PI * mu / (c.powi(2) * a * (1.0 - e.powi(2)))
+    }
+
+    #[test]
+    fn reversed_runs_retrace_their_steps_back_to_the_start() {
+        let mut simulation =
+            Simulation::new(Some(0.0), None, Some(1e-3)).with_constants(Constants {
+                g: Some(1.0),
+                ..Default::default()
+            });
+        simulation.add_body(Body::new(
+            String::from("star"),
+            1.0,
+            0.01,
+            Vector3::default(),
+            Vector3::default(),
+            Default::default(),
+        ));
+        simulation.add_body(Body::new(
+            String::from("planet"),
+            1e-3,
+            0.001,
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Default::default(),
+        ));
+        let steps = 1000;
+        let mut run = OwningRun::from(simulation);
+        let forward: Vec<RunStep<3>> = run.by_ref().take(steps).collect();
+        run.reverse();
+        let backward: Vec<RunStep<3>> = run.collect();
+        // Every step is retraced, ending at the start, up to the
+        // integrator's truncation error
+        assert_eq!(backward.len(), steps);
+        for (forward, backward) in forward.iter().zip(backward.iter().rev()) {
+            assert_abs_diff_eq!(forward.t, backward.t, epsilon = 1e-4);
+            for label in ["star", "planet"] {
+                assert_vectors_eq(
+                    &forward.body_map[label].position,
+                    &backward.body_map[label].position,
+                    1e-3,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn relativity_precesses_orbits_by_the_predicted_angle() {
+        // In units with G = 1 and a slow speed of light the effect is large
+        // enough to measure in single precision
+        let (a, e, c): (f32, f32, f32) = (1.0, 0.2, 30.0);
+        let speed = ((1.0 + e) / (a * (1.0 - e))).sqrt();
+        let orbits = 4.0;
+        let mut simulation = Simulation::new(
+            Some(0.0),
+            Some((orbits + 0.5) * 2.0 * std::f32::consts::PI),
+            Some(1e-3),
+        )
+        .with_constants(Constants {
+            g: Some(1.0),
+            c: Some(c),
+            ..Default::default()
+        });
+        simulation.add_body(Body::new(
+            String::from("star"),
+            1.0,
+            0.01,
+            Vector3::default(),
+            Vector3::default(),
+            Default::default(),
+        ));
+        simulation.add_body(Body::new(
+            String::from("planet"),
+            1e-6,
+            0.001,
+            Vector3::new(a * (1.0 - e), 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -speed),
+            Default::default(),
+        ));
+        // Angle of the planet at its last closest approach. The integrator
+        // precesses orbits slightly by itself, so this is compared against
+        // the same orbit without relativity.
+        let last_periapsis_angle = |simulation: &Simulation<3>| {
+            let mut angle = 0.0;
+            let mut distances = [f32::MAX; 2];
+            let mut previous_angle = 0.0;
+            for step in Run::from(simulation) {
+                let r = &step.body_map["planet"].position - &step.body_map["star"].position;
+                let distance = r.magnitude();
+                if distances[1] < distances[0] && distances[1] <= distance {
+                    angle = previous_angle;
+                }
+                distances = [distances[1], distance];
+                previous_angle = r.z().atan2(r.x());
+            }
+            angle
+        };
+        let newtonian = last_periapsis_angle(&simulation);
+        let relativistic = last_periapsis_angle(&simulation.clone().with_relativity());
+        // Orbiting towards -z is clockwise in the x-z plane
+        let advance = (newtonian - relativistic) as f64;
+        let predicted = orbits as f64 * relativistic_precession(1.0, c as f64, a as f64, e as f64);
+        assert_abs_diff_eq!(advance / predicted, 1.0, epsilon = 0.05);
+    }
+}
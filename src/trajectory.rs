@@ -0,0 +1,284 @@
+use crate::math::{Quaternion, Vector, Vector3};
+use crate::simulation::BodyMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Identifies the binary trajectory format, followed by its version
+const MAGIC: &[u8; 8] = b"SIMTRAJ1";
+/// Most bodies space is reserved for up front, so a corrupt body count
+/// fails on reaching the end of the file rather than allocating
+const MAX_RESERVED_BODIES: usize = 1024;
+
+pub type TrajectoryResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// The state of every recorded body at a single point in time, in the
+/// order of the trajectory's labels.
+#[derive(Debug, Clone)]
+pub struct TrajectoryFrame {
+    pub t: f32,
+    pub positions: Vec<Vector3>,
+    /// Orientations of the bodies, if they were recorded
+    pub orientations: Option<Vec<Quaternion>>,
+}
+
+/// A previously recorded simulation run.
+#[derive(Debug, Default)]
+pub struct Trajectory {
+    pub labels: Vec<String>,
+    pub frames: Vec<TrajectoryFrame>,
+}
+
+impl Trajectory {
+    /// Loads a trajectory, as CSV if the file has a `.csv` extension and
+    /// in the binary trajectory format otherwise.
+    pub fn load(path: &Path) -> TrajectoryResult<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Self::from_csv(&fs::read_to_string(path)?),
+            _ => Self::read_binary(&mut BufReader::new(File::open(path)?)),
+        }
+    }
+
+    /// Parses the output of `CsvAdapter`, which has a `t` column followed by
+    /// `<label>.<n>` columns for each position component of each body.
+    pub fn from_csv(csv: &str) -> TrajectoryResult<Self> {
+        let mut lines = csv.lines().filter(|l| !l.trim().is_empty());
+        let header = lines.next().ok_or("trajectory CSV is empty")?;
+        let mut columns = header.split(',');
+        if columns.next() != Some("t") {
+            return Err("trajectory CSV must start with a t column".into());
+        }
+
+        // Map each column to the index of its body and position component
+        let mut labels: Vec<String> = Vec::new();
+        let mut column_targets = Vec::new();
+        for column in columns {
+            let (label, component) = column
+                .rsplit_once('.')
+                .ok_or_else(|| format!("invalid trajectory CSV column {}", column))?;
+            let component = match component.parse()? {
+                0 => return Err(format!("invalid trajectory CSV column {}", column).into()),
+                component => component,
+            };
+            if labels.last().map(String::as_str) != Some(label) {
+                labels.push(label.to_string());
+            }
+            column_targets.push((labels.len() - 1, component - 1));
+        }
+
+        let mut frames = Vec::new();
+        for line in lines {
+            let mut values = line.split(',');
+            let t: f32 = values.next().unwrap_or_default().trim().parse()?;
+            // Playback interpolates between frames, which needs them in order
+            if frames.last().is_some_and(|f: &TrajectoryFrame| f.t >= t) {
+                return Err(
+                    format!("trajectory CSV times must increase, but {} doesn't", t).into(),
+                );
+            }
+            let mut positions = vec![Vector3::default(); labels.len()];
+            for ((body, component), value) in column_targets.iter().zip(values) {
+                if *component < 3 {
+                    positions[*body][*component] = value.trim().parse()?;
+                }
+            }
+            frames.push(TrajectoryFrame {
+                t,
+                positions,
+                orientations: None,
+            });
+        }
+        Ok(Self { labels, frames })
+    }
+
+    pub fn read_binary(r: &mut impl Read) -> TrajectoryResult<Self> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err("not a binary trajectory file".into());
+        }
+        let n_bodies = read_u32(r)? as usize;
+        let dimensions = read_u32(r)? as usize;
+        let reserved = n_bodies.min(MAX_RESERVED_BODIES);
+        let mut labels = Vec::with_capacity(reserved);
+        for _ in 0..n_bodies {
+            let len = read_u32(r)? as u64;
+            let mut label = Vec::new();
+            if r.take(len).read_to_end(&mut label)? as u64 != len {
+                return Err("trajectory file ends within a label".into());
+            }
+            labels.push(String::from_utf8(label)?);
+        }
+
+        let mut frames = Vec::new();
+        loop {
+            let t = match read_f32(r) {
+                Ok(t) => t,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            let mut positions = Vec::with_capacity(reserved);
+            let mut orientations = Vec::with_capacity(reserved);
+            for _ in 0..n_bodies {
+                let mut position = Vector3::default();
+                for i in 0..dimensions {
+                    let component = read_f32(r)?;
+                    if i < 3 {
+                        position[i] = component;
+                    }
+                }
+                // Velocities are recorded but not needed for playback
+                for _ in 0..dimensions {
+                    read_f32(r)?;
+                }
+                positions.push(position);
+                orientations.push(Quaternion::new(
+                    read_f32(r)?,
+                    read_f32(r)?,
+                    read_f32(r)?,
+                    read_f32(r)?,
+                ));
+            }
+            frames.push(TrajectoryFrame {
+                t,
+                positions,
+                orientations: Some(orientations),
+            });
+        }
+        Ok(Self { labels, frames })
+    }
+}
+
+/// Writes the binary trajectory format incrementally, one step at a time.
+pub struct TrajectoryWriter<W: Write> {
+    w: W,
+    labels: Vec<String>,
+}
+
+impl<W: Write> TrajectoryWriter<W> {
+    pub fn new<const N: usize>(mut w: W, labels: Vec<String>) -> io::Result<Self> {
+        w.write_all(MAGIC)?;
+        w.write_all(&(labels.len() as u32).to_le_bytes())?;
+        w.write_all(&(N as u32).to_le_bytes())?;
+        for label in &labels {
+            w.write_all(&(label.len() as u32).to_le_bytes())?;
+            w.write_all(label.as_bytes())?;
+        }
+        Ok(Self { w, labels })
+    }
+
+    /// Appends the state of the bodies at time t. Bodies missing from the
+    /// map are written at the origin.
+    pub fn write_step<const N: usize>(&mut self, t: f32, body_map: &BodyMap<N>) -> io::Result<()> {
+        self.w.write_all(&t.to_le_bytes())?;
+        for label in &self.labels {
+            let (position, velocity, orientation) = match body_map.get(label) {
+                Some(body) => (body.position, body.velocity, body.attitude.orientation),
+                None => (Vector::default(), Vector::default(), Quaternion::IDENTITY),
+            };
+            for i in 0..N {
+                self.w.write_all(&position[i].to_le_bytes())?;
+            }
+            for i in 0..N {
+                self.w.write_all(&velocity[i].to_le_bytes())?;
+            }
+            for component in [orientation.w, orientation.x, orientation.y, orientation.z] {
+                self.w.write_all(&component.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+impl TrajectoryWriter<BufWriter<File>> {
+    pub fn create<const N: usize>(path: &Path, labels: Vec<String>) -> io::Result<Self> {
+        Self::new::<N>(BufWriter::new(File::create(path)?), labels)
+    }
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32(r: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Body, SpinCharacteristics};
+
+    #[test]
+    fn parsing_csv_adapter_output_works() {
+        let csv = "t,Moon.1,Moon.2,Moon.3,Earth.1,Earth.2,Earth.3\n\
+                   0.0,1,2,3,0,0,0\n\
+                   200.0,4,5,6,0,0,-1\n";
+        let trajectory = Trajectory::from_csv(csv).unwrap();
+        assert_eq!(trajectory.labels, vec!["Moon", "Earth"]);
+        assert_eq!(trajectory.frames.len(), 2);
+        assert_eq!(trajectory.frames[1].t, 200.0);
+        assert_eq!(
+            trajectory.frames[1].positions[0],
+            Vector3::new(4.0, 5.0, 6.0)
+        );
+        assert_eq!(
+            trajectory.frames[1].positions[1],
+            Vector3::new(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn binary_trajectories_round_trip() {
+        let mut body_map = BodyMap::<2>::new();
+        let spin = SpinCharacteristics {
+            angle: 0.5,
+            ..Default::default()
+        };
+        let position = Vector::from([1.0, 2.0]);
+        let body = Body::new("a".into(), 1.0, 1.0, position, Vector::default(), spin);
+        body_map.insert("a".into(), body);
+
+        let mut bytes = Vec::new();
+        let mut writer = TrajectoryWriter::new::<2>(&mut bytes, vec!["a".into()]).unwrap();
+        writer.write_step(0.0, &body_map).unwrap();
+        writer.write_step(1.0, &body_map).unwrap();
+
+        let trajectory = Trajectory::read_binary(&mut bytes.as_slice()).unwrap();
+        assert_eq!(trajectory.labels, vec!["a"]);
+        assert_eq!(trajectory.frames.len(), 2);
+        assert_eq!(trajectory.frames[1].t, 1.0);
+        assert_eq!(
+            trajectory.frames[1].positions[0],
+            Vector3::new(1.0, 2.0, 0.0)
+        );
+        let orientation = Quaternion::from_axis_angle(&Vector3::new(0.0, 1.0, 0.0), 0.5);
+        assert_eq!(trajectory.frames[1].orientations, Some(vec![orientation]));
+    }
+
+    #[test]
+    fn corrupt_lengths_are_errors() {
+        let mut bytes = MAGIC.to_vec();
+        for value in [u32::MAX, 3, u32::MAX] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(b"Moon");
+        assert!(Trajectory::read_binary(&mut bytes.as_slice()).is_err());
+        assert!(Trajectory::from_csv("t,Moon.0\n0.0,1\n").is_err());
+    }
+
+    #[test]
+    fn csv_frames_must_be_in_time_order() {
+        assert!(Trajectory::from_csv("t,Moon.1\n0.1,1\n0.1,2\n").is_err());
+        assert!(Trajectory::from_csv("t,Moon.1\n0.1,1\n0.15,2\n").is_ok());
+    }
+}